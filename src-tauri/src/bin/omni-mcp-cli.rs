@@ -0,0 +1,4 @@
+// 命令行工具，与应用共用数据库，用于在新机器和 CI 中脚本化配置
+fn main() {
+    std::process::exit(tauri_app_lib::cli::run())
+}
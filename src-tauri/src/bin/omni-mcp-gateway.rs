@@ -0,0 +1,98 @@
+// MCP 网关的 stdio 辅助程序
+//
+// 从 stdin 逐行读取 JSON-RPC 消息，转发到应用的本地网关 (POST /mcp)，
+// 把响应写到 stdout，供只支持 stdio 服务器的客户端使用。
+// 端口和令牌默认读取应用保存的网关设置，也可以通过 --port 或 OMNI_MCP_GATEWAY_PORT
+// 以及 OMNI_MCP_GATEWAY_TOKEN 指定，需要先在应用中启用网关。
+
+use std::io::{BufRead, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use tauri_app_lib::mcp_gateway::{self, DEFAULT_GATEWAY_PORT};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 读取网关端口和令牌，命令行参数和环境变量优先于应用中的设置
+fn gateway_settings() -> (u16, String) {
+    let (saved_port, saved_token) = mcp_gateway::client_settings().unwrap_or_else(|e| {
+        eprintln!("读取网关设置失败: {}", e);
+        (DEFAULT_GATEWAY_PORT, String::new())
+    });
+
+    let mut port = std::env::var("OMNI_MCP_GATEWAY_PORT").ok().and_then(|port| port.parse().ok());
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--port" {
+            if let Some(value) = args.next().and_then(|port| port.parse().ok()) {
+                port = Some(value);
+            }
+        }
+    }
+    let token = std::env::var("OMNI_MCP_GATEWAY_TOKEN").ok()
+        .filter(|token| !token.is_empty())
+        .unwrap_or(saved_token);
+    (port.unwrap_or(saved_port), token)
+}
+
+// 发送一条消息，返回响应体，通知的 202 响应没有内容
+fn post_message(port: u16, token: &str, message: &str) -> Result<Option<String>, String> {
+    let addr = ("127.0.0.1", port);
+    let addr = std::net::ToSocketAddrs::to_socket_addrs(&addr)
+        .map_err(|e| format!("解析网关地址失败: {}", e))?
+        .next()
+        .ok_or_else(|| "无法解析网关地址".to_string())?;
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)
+        .map_err(|e| format!("无法连接 MCP 网关 (端口 {})，请确认应用中已启用网关: {}", port, e))?;
+
+    write!(
+        stream,
+        "POST /mcp HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nAuthorization: Bearer {}\r\nContent-Type: application/json\r\nAccept: application/json, text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        port, token, message.len(), message
+    ).map_err(|e| format!("发送请求失败: {}", e))?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(|e| format!("读取响应失败: {}", e))?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    match status {
+        "200" => Ok(Some(body.trim().to_string())),
+        "202" => Ok(None),
+        "401" => Err("网关令牌无效，请检查应用中的网关令牌或 OMNI_MCP_GATEWAY_TOKEN".to_string()),
+        _ => Err(format!("网关返回 {}: {}", status, body.trim())),
+    }
+}
+
+fn main() {
+    let (port, token) = gateway_settings();
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    for line in stdin.lock().lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match post_message(port, &token, &line) {
+            Ok(Some(response)) => {
+                if writeln!(stdout, "{}", response).and_then(|_| stdout.flush()).is_err() {
+                    break;
+                }
+            },
+            Ok(None) => {},
+            Err(e) => {
+                eprintln!("{}", e);
+                // 请求失败时返回 JSON-RPC 错误，避免客户端一直等待
+                let id = serde_json::from_str::<serde_json::Value>(&line).ok()
+                    .and_then(|message| message.get("id").cloned());
+                if let Some(id) = id {
+                    let error = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32603, "message": e },
+                    });
+                    let _ = writeln!(stdout, "{}", error).and_then(|_| stdout.flush());
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,519 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::mcp_installer;
+use crate::save_mcp_config::{self, get_mcp_servers_root_dir, McpServerConfig};
+use crate::tools::{self, RuntimeStatus};
+
+// 离线安装包格式版本，格式不兼容时递增
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+
+// 安装包中的一个托管路径
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledPath {
+    // 注册表中的路径写法（含占位符），导入时在目标机器上展开
+    pub target: String,
+    // 在安装包中的相对路径
+    pub archive: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledTool {
+    pub id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub paths: Vec<BundledPath>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledServer {
+    pub config: McpServerConfig,
+    // 服务器安装目录在安装包中的相对路径，没有安装目录时为空
+    pub archive: Option<String>,
+}
+
+// 安装包清单
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub created_at: u64,
+    // 导出机器的系统和架构，工具的二进制文件只能在相同平台上使用
+    pub os: String,
+    pub arch: String,
+    pub tools: Vec<BundledTool>,
+    pub servers: Vec<BundledServer>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportReport {
+    pub path: String,
+    pub size: u64,
+    pub tools: Vec<String>,
+    pub servers: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub tools_installed: Vec<String>,
+    pub tools_skipped: Vec<String>,
+    pub servers_imported: Vec<String>,
+    pub servers_skipped: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+// 创建临时工作目录
+fn create_work_dir(prefix: &str) -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join(format!("{}-{}-{}", prefix, std::process::id(), now_secs()));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| format!("清理临时目录失败: {}", e))?;
+    }
+    std::fs::create_dir_all(&dir).map_err(|e| format!("创建临时目录失败: {}", e))?;
+    Ok(dir)
+}
+
+// 复制文件或目录，保留符号链接（node 的 bin 目录依赖相对链接）
+fn copy_path(src: &Path, dst: &Path) -> Result<(), String> {
+    let metadata = std::fs::symlink_metadata(src)
+        .map_err(|e| format!("读取 {:?} 失败: {}", src, e))?;
+
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("创建目录 {:?} 失败: {}", parent, e))?;
+    }
+
+    #[cfg(unix)]
+    if metadata.file_type().is_symlink() {
+        let link = std::fs::read_link(src).map_err(|e| format!("读取链接 {:?} 失败: {}", src, e))?;
+        return std::os::unix::fs::symlink(&link, dst)
+            .map_err(|e| format!("创建链接 {:?} 失败: {}", dst, e));
+    }
+
+    if metadata.is_dir() || (metadata.file_type().is_symlink() && src.is_dir()) {
+        std::fs::create_dir_all(dst).map_err(|e| format!("创建目录 {:?} 失败: {}", dst, e))?;
+        let entries = std::fs::read_dir(src).map_err(|e| format!("读取目录 {:?} 失败: {}", src, e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("读取目录 {:?} 失败: {}", src, e))?;
+            copy_path(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(src, dst)
+            .map(|_| ())
+            .map_err(|e| format!("复制 {:?} 失败: {}", src, e))
+    }
+}
+
+fn run_tar(args: &[&str]) -> Result<(), String> {
+    println!("执行 tar {:?}", args);
+    let output = Command::new("tar")
+        .args(args)
+        .output()
+        .map_err(|e| format!("执行 tar 失败: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("tar 执行失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+// 收集要导出的工具，只导出应用安装的工具，系统安装的工具无法在其他机器上还原
+fn collect_tools(
+    work_dir: &Path,
+    selected: Option<&[String]>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<BundledTool>, String> {
+    let mut bundled = Vec::new();
+    // 多个工具共用同一个目录时只打包一次
+    let mut archived: HashMap<String, String> = HashMap::new();
+
+    for tool in tools::load_registry()? {
+        let wanted = selected.is_none_or(|ids| ids.contains(&tool.id));
+        if !wanted {
+            continue;
+        }
+
+        let Some(executable) = tool.find_executable() else {
+            if selected.is_some() {
+                warnings.push(format!("{} 未安装，已跳过", tool.name));
+            }
+            continue;
+        };
+        if !tool.is_managed(&executable) {
+            if selected.is_some() {
+                warnings.push(format!("{} ({}) 不是由本应用安装的，已跳过", tool.name, executable.to_string_lossy()));
+            }
+            continue;
+        }
+
+        let mut paths = Vec::new();
+        for (index, (target, path)) in tool.managed_path_entries().into_iter().enumerate() {
+            let archive = match archived.get(&target) {
+                Some(archive) => archive.clone(),
+                None => {
+                    let archive = format!("tools/{}/{}", tool.id, index);
+                    println!("打包 {} -> {}", path.to_string_lossy(), archive);
+                    copy_path(&path, &work_dir.join(&archive))?;
+                    archived.insert(target.clone(), archive.clone());
+                    archive
+                },
+            };
+            paths.push(BundledPath { target, archive });
+        }
+
+        bundled.push(BundledTool {
+            id: tool.id.clone(),
+            name: tool.name.clone(),
+            version: tool.version(),
+            paths,
+        });
+    }
+
+    Ok(bundled)
+}
+
+// 收集要导出的 MCP 服务器配置和安装目录
+async fn collect_servers(
+    work_dir: &Path,
+    selected: Option<&[String]>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<BundledServer>, String> {
+    let root = get_mcp_servers_root_dir()?;
+    let mut bundled = Vec::new();
+
+    for mut config in save_mcp_config::get_all_mcp_servers(None).await? {
+        let wanted = selected.is_none_or(|names| names.contains(&config.name));
+        if !wanted {
+            continue;
+        }
+        config.runtime_status = None;
+
+        let server_dir = config.install_dir.as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| root.join(&config.name));
+        let archive = if server_dir.is_dir() {
+            let archive = format!("servers/{}", config.name);
+            println!("打包 {} -> {}", server_dir.to_string_lossy(), archive);
+            copy_path(&server_dir, &work_dir.join(&archive))?;
+            Some(archive)
+        } else {
+            None
+        };
+
+        bundled.push(BundledServer { config, archive });
+    }
+
+    if let Some(names) = selected {
+        for name in names {
+            if !bundled.iter().any(|server| &server.config.name == name) {
+                warnings.push(format!("未找到服务器 {}，已跳过", name));
+            }
+        }
+    }
+
+    Ok(bundled)
+}
+
+// 导出离线安装包：应用安装的工具、MCP 服务器目录以及 mcpServers 中的配置
+//
+// 安装包是 tar.gz 文件，服务器配置中的环境变量会原样导出，请妥善保管
+pub async fn export_bundle(
+    path: &Path,
+    tools: Option<&[String]>,
+    servers: Option<&[String]>,
+) -> Result<ExportReport, String> {
+    info!("开始导出离线安装包: {:?}", path);
+
+    let work_dir = create_work_dir("omni-bundle-export")?;
+    let result = async {
+        let mut warnings = Vec::new();
+        let manifest = BundleManifest {
+            format_version: BUNDLE_FORMAT_VERSION,
+            created_at: now_secs(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            tools: collect_tools(&work_dir, tools, &mut warnings)?,
+            servers: collect_servers(&work_dir, servers, &mut warnings).await?,
+        };
+
+        let manifest_json = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| format!("序列化安装包清单失败: {}", e))?;
+        std::fs::write(work_dir.join(MANIFEST_FILE), manifest_json)
+            .map_err(|e| format!("写入安装包清单失败: {}", e))?;
+
+        run_tar(&["-czf", &path.to_string_lossy(), "-C", &work_dir.to_string_lossy(), "."])?;
+
+        let size = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or_default();
+        Ok(ExportReport {
+            path: path.to_string_lossy().to_string(),
+            size,
+            tools: manifest.tools.iter().map(|tool| tool.id.clone()).collect(),
+            servers: manifest.servers.iter().map(|server| server.config.name.clone()).collect(),
+            warnings,
+        })
+    }.await;
+
+    if let Err(e) = std::fs::remove_dir_all(&work_dir) {
+        warn!("清理临时目录失败: {}", e);
+    }
+    result
+}
+
+// 安装包中的相对路径不能跳出解压目录
+fn archive_path(work_dir: &Path, archive: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(archive);
+    if relative.is_absolute() || relative.components().any(|component| matches!(component, std::path::Component::ParentDir)) {
+        return Err(format!("安装包中的路径无效: {}", archive));
+    }
+    Ok(work_dir.join(relative))
+}
+
+fn import_tools(work_dir: &Path, manifest: &BundleManifest, report: &mut ImportReport) -> Result<(), String> {
+    if manifest.tools.is_empty() {
+        return Ok(());
+    }
+    if manifest.os != std::env::consts::OS || manifest.arch != std::env::consts::ARCH {
+        report.warnings.push(format!(
+            "安装包中的工具是为 {}-{} 打包的，当前系统为 {}-{}，已跳过所有工具",
+            manifest.os, manifest.arch, std::env::consts::OS, std::env::consts::ARCH
+        ));
+        report.tools_skipped.extend(manifest.tools.iter().map(|tool| tool.id.clone()));
+        return Ok(());
+    }
+
+    for bundled in &manifest.tools {
+        let tool = match tools::find_tool(&bundled.id) {
+            Ok(tool) => tool,
+            Err(e) => {
+                report.warnings.push(format!("{}，已跳过", e));
+                report.tools_skipped.push(bundled.id.clone());
+                continue;
+            },
+        };
+        if tool.check_installed() {
+            println!("{} 已安装，跳过", bundled.name);
+            report.tools_skipped.push(bundled.id.clone());
+            continue;
+        }
+
+        // 只还原到本机注册表中该工具的托管路径，不使用安装包中的任意路径
+        let targets: Option<Vec<(PathBuf, &BundledPath)>> = bundled.paths.iter()
+            .map(|path| tool.managed_path_target(&path.target).map(|target| (target, path)))
+            .collect();
+        let Some(targets) = targets else {
+            report.warnings.push(format!("安装包中 {} 的路径不是注册表中的托管路径，已跳过", bundled.name));
+            report.tools_skipped.push(bundled.id.clone());
+            continue;
+        };
+
+        for (target, path) in targets {
+            if target.exists() {
+                // 共用目录的工具（如 node 和 npx）已经由前一个工具还原
+                continue;
+            }
+            println!("还原 {} -> {}", path.archive, target.to_string_lossy());
+            copy_path(&archive_path(work_dir, &path.archive)?, &target)?;
+        }
+        info!("{} {} 已从离线安装包还原", bundled.name, bundled.version.as_deref().unwrap_or_default());
+        report.tools_installed.push(bundled.id.clone());
+    }
+
+    Ok(())
+}
+
+fn import_servers(work_dir: &Path, manifest: &BundleManifest, overwrite: bool, report: &mut ImportReport) -> Result<(), String> {
+    let root = get_mcp_servers_root_dir()?;
+    if !manifest.servers.is_empty() && (manifest.os != std::env::consts::OS || manifest.arch != std::env::consts::ARCH) {
+        report.warnings.push(format!(
+            "服务器目录是在 {}-{} 上安装的，包含原生模块的服务器可能无法在当前系统运行",
+            manifest.os, manifest.arch
+        ));
+    }
+
+    for bundled in &manifest.servers {
+        let name = &bundled.config.name;
+        // 名称来自安装包清单，包含 .. 或路径分隔符时会把目录还原到服务器目录之外
+        if let Err(e) = mcp_installer::validate_server_name(name) {
+            report.servers_skipped.push(name.clone());
+            report.warnings.push(format!("{}，已跳过", e));
+            continue;
+        }
+        let server_dir = root.join(name);
+
+        if let Some(archive) = &bundled.archive {
+            let has_files = std::fs::read_dir(&server_dir)
+                .map(|mut entries| entries.next().is_some())
+                .unwrap_or(false);
+            if has_files && !overwrite {
+                report.servers_skipped.push(name.clone());
+                report.warnings.push(format!("服务器目录 {} 已存在，已跳过", server_dir.to_string_lossy()));
+                continue;
+            }
+            if server_dir.exists() {
+                std::fs::remove_dir_all(&server_dir)
+                    .map_err(|e| format!("删除服务器目录 {:?} 失败: {}", server_dir, e))?;
+            }
+            println!("还原 {} -> {}", archive, server_dir.to_string_lossy());
+            copy_path(&archive_path(work_dir, archive)?, &server_dir)?;
+        }
+
+        // 启动命令中引用的原安装目录替换为当前机器上的目录
+        let install_dir = bundled.archive.as_ref().map(|_| server_dir.to_string_lossy().to_string());
+        let relocate = |value: &str| match (&bundled.config.install_dir, &install_dir) {
+            (Some(old_dir), Some(new_dir)) => value.replace(old_dir.as_str(), new_dir),
+            _ => value.to_string(),
+        };
+
+        let config = McpServerConfig {
+            name: name.clone(),
+            description: bundled.config.description.clone(),
+            command: relocate(&bundled.config.command),
+            args: bundled.config.args.iter().map(|arg| relocate(arg)).collect(),
+            is_active: bundled.config.is_active,
+            env: bundled.config.env.clone(),
+            type_: bundled.config.type_.clone(),
+            base_url: bundled.config.base_url.clone(),
+            install_dir: bundled.config.install_dir.as_ref().and(install_dir.clone()),
+            installed_commit: bundled.config.installed_commit.clone(),
+            upgraded_at: bundled.config.upgraded_at.clone(),
+            package: bundled.config.package.clone(),
+            docker: bundled.config.docker.clone(),
+            lifecycle: bundled.config.lifecycle.clone(),
+            runtime_status: None,
+        };
+        if let Some(RuntimeStatus::MissingRuntime { tool, command }) = save_mcp_config::check_server_runtime(&config) {
+            report.warnings.push(format!("{}: 命令 {} 需要安装 {}", name, command, tool));
+        }
+        save_mcp_config::save_mcp_server_config(config)?;
+        report.servers_imported.push(name.clone());
+    }
+
+    Ok(())
+}
+
+// 从离线安装包安装工具和 MCP 服务器，整个过程不需要网络
+//
+// 已安装的工具不会被覆盖；已存在的服务器目录只在 overwrite 为 true 时替换
+pub fn import_bundle(path: &Path, overwrite: bool) -> Result<ImportReport, String> {
+    info!("开始导入离线安装包: {:?}", path);
+    if !path.is_file() {
+        return Err(format!("安装包不存在: {}", path.to_string_lossy()));
+    }
+
+    let work_dir = create_work_dir("omni-bundle-import")?;
+    let result = (|| {
+        run_tar(&["-xzf", &path.to_string_lossy(), "-C", &work_dir.to_string_lossy()])?;
+
+        let manifest_json = std::fs::read_to_string(work_dir.join(MANIFEST_FILE))
+            .map_err(|e| format!("读取安装包清单失败: {}", e))?;
+        let manifest: BundleManifest = serde_json::from_str(&manifest_json)
+            .map_err(|e| format!("解析安装包清单失败: {}", e))?;
+        if manifest.format_version > BUNDLE_FORMAT_VERSION {
+            return Err(format!("安装包格式版本 {} 过新，请升级应用后再导入", manifest.format_version));
+        }
+
+        let mut report = ImportReport::default();
+        import_tools(&work_dir, &manifest, &mut report)?;
+        import_servers(&work_dir, &manifest, overwrite, &mut report)?;
+        Ok(report)
+    })();
+
+    if let Err(e) = std::fs::remove_dir_all(&work_dir) {
+        warn!("清理临时目录失败: {}", e);
+    }
+    result
+}
+
+// 导出离线安装包，tools 和 servers 为空时导出全部
+#[tauri::command]
+pub async fn export_offline_bundle(
+    path: String,
+    tools: Option<Vec<String>>,
+    servers: Option<Vec<String>>,
+) -> Result<ExportReport, String> {
+    export_bundle(Path::new(&path), tools.as_deref(), servers.as_deref()).await
+}
+
+// 导入离线安装包
+#[tauri::command]
+pub async fn import_offline_bundle(path: String, overwrite: Option<bool>) -> Result<ImportReport, String> {
+    import_bundle(Path::new(&path), overwrite.unwrap_or(false))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_env::TestEnv;
+
+    fn bundled_server(name: &str, archive: Option<&str>) -> BundledServer {
+        BundledServer {
+            config: serde_json::from_value(serde_json::json!({
+                "name": name,
+                "command": "node",
+                "args": ["index.js"],
+                "isActive": true,
+                "installDir": "/old/server",
+            }))
+            .unwrap(),
+            archive: archive.map(String::from),
+        }
+    }
+
+    #[test]
+    fn import_skips_servers_with_traversal_names() {
+        let mut env = TestEnv::lock();
+        let root = std::env::temp_dir().join(format!("omni-mcp-bundle-test-{}", std::process::id()));
+        std::fs::remove_dir_all(&root).ok();
+        env.isolate_home(&root);
+
+        // 名称中的 .. 指向 HOME 下已有的目录
+        let victim = root.join("home").join("victim");
+        std::fs::create_dir_all(&victim).unwrap();
+        std::fs::write(victim.join("keep.txt"), "keep").unwrap();
+
+        let content = root.join("content");
+        std::fs::create_dir_all(content.join("servers").join("payload")).unwrap();
+        std::fs::write(content.join("servers").join("payload").join("index.js"), "// payload").unwrap();
+        let manifest = BundleManifest {
+            format_version: BUNDLE_FORMAT_VERSION,
+            created_at: now_secs(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            tools: Vec::new(),
+            servers: vec![
+                bundled_server("../../victim", Some("servers/payload")),
+                bundled_server("good", Some("servers/payload")),
+            ],
+        };
+        std::fs::write(content.join(MANIFEST_FILE), serde_json::to_string(&manifest).unwrap()).unwrap();
+        let bundle = root.join("bundle.tar.gz");
+        run_tar(&["-czf", &bundle.to_string_lossy(), "-C", &content.to_string_lossy(), "."]).unwrap();
+
+        let report = import_bundle(&bundle, true).unwrap();
+        assert_eq!(report.servers_skipped, ["../../victim"]);
+        assert_eq!(report.servers_imported, ["good"]);
+        assert_eq!(std::fs::read_to_string(victim.join("keep.txt")).unwrap(), "keep");
+        assert!(!victim.join("index.js").exists());
+        let server_dir = get_mcp_servers_root_dir().unwrap().join("good");
+        assert!(server_dir.join("index.js").exists());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,457 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::docker_runtime;
+use crate::doctor;
+use crate::mcp_client::{self, McpClient, NoopHandler};
+use crate::mcp_lifecycle::Lifecycle;
+use crate::mcp_tool_cache;
+use crate::model_config::{self, ModelConfig};
+use crate::save_mcp_config::{self, McpServerConfig};
+use crate::tools::{self, InstallProgress};
+
+const USAGE: &str = "omni-mcp-cli: 无需打开应用即可管理 MCP 服务器、模型和工具
+
+用法:
+  omni-mcp-cli servers import <文件|->      导入 mcpServers 配置，- 表示从 stdin 读取
+  omni-mcp-cli servers list [--json]         列出服务器
+  omni-mcp-cli servers enable <名称>         启用服务器
+  omni-mcp-cli servers disable <名称>        停用服务器
+  omni-mcp-cli servers start <名称> [--check]
+                                             在前台运行服务器，按 Ctrl+C 停止；
+                                             --check 只检查能否启动并更新工具列表
+  omni-mcp-cli servers stop <名称>           停止记录中的服务器进程和容器
+  omni-mcp-cli models list [--json]          列出模型配置
+  omni-mcp-cli models set <provider> [--url <地址>] [--model <模型>] [--key <密钥|->]
+                                    [--endpoint <地址>] [--method <路径>]
+                                             新建或修改模型配置，--key - 表示从 stdin 读取
+  omni-mcp-cli tools status [--json]         查看工具安装状态
+  omni-mcp-cli tools install <工具>...       安装工具，已安装的工具会跳过
+  omni-mcp-cli doctor [--json]               环境诊断，存在不可写的目录时返回非零状态";
+
+// 需要取值的选项，其余 -- 开头的参数都是开关
+const VALUE_OPTIONS: [&str; 5] = ["url", "model", "key", "endpoint", "method"];
+const FLAGS: [&str; 3] = ["json", "check", "help"];
+// 等待 docker run 写入容器 id 的时间
+const CONTAINER_ID_TIMEOUT: Duration = Duration::from_secs(5);
+// 前台运行时检查服务器是否退出的间隔
+const WAIT_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args { positional: Vec::new(), options: HashMap::new(), flags: Vec::new() };
+        while let Some(arg) = args.next() {
+            if arg == "-h" {
+                parsed.flags.push("help".to_string());
+                continue;
+            }
+            let Some(name) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg);
+                continue;
+            };
+            // 支持 --key=value 和 --key value 两种写法
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (name, None),
+            };
+            if VALUE_OPTIONS.contains(&name) {
+                let value = inline_value.or_else(|| args.next())
+                    .ok_or_else(|| format!("选项 --{} 缺少值", name))?;
+                parsed.options.insert(name.to_string(), value);
+            } else if FLAGS.contains(&name) {
+                parsed.flags.push(name.to_string());
+            } else {
+                return Err(format!("未知选项: {}", arg));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    fn option(&self, name: &str) -> Option<String> {
+        self.options.get(name).cloned()
+    }
+}
+
+// 命令行入口，返回进程退出码
+pub fn run() -> i32 {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 2;
+        },
+    };
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return 0;
+    }
+
+    let positional: Vec<&str> = args.positional.iter().map(String::as_str).collect();
+    let result = match positional.as_slice() {
+        ["servers", "import", file] => servers_import(file),
+        ["servers", "list"] => servers_list(args.flag("json")),
+        ["servers", "enable", name] => servers_set_active(name, true),
+        ["servers", "disable", name] => servers_set_active(name, false),
+        ["servers", "start", name] => servers_start(name, args.flag("check")),
+        ["servers", "stop", name] => servers_stop(name),
+        ["models", "list"] => models_list(args.flag("json")),
+        ["models", "set", provider] => models_set(provider, &args),
+        ["tools", "status"] => tools_status(args.flag("json")),
+        ["tools", "install", ids @ ..] if !ids.is_empty() => tools_install(ids),
+        ["doctor"] => run_doctor(args.flag("json")),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        },
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            1
+        },
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("序列化结果失败: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+// 读取文件内容，- 表示 stdin
+fn read_input(path: &str) -> Result<String, String> {
+    if path == "-" {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content).map_err(|e| format!("读取 stdin 失败: {}", e))?;
+        return Ok(content);
+    }
+    std::fs::read_to_string(path).map_err(|e| format!("读取文件 {} 失败: {}", path, e))
+}
+
+fn servers_import(file: &str) -> Result<(), String> {
+    let content = read_input(file)?;
+    let message = save_mcp_config::parse_mcp_config(&content)?;
+    println!("{}", message.trim_end());
+    Ok(())
+}
+
+fn server_type(config: &McpServerConfig) -> &str {
+    match (&config.docker, config.type_.as_deref()) {
+        (Some(_), _) => "docker",
+        (None, Some(type_)) if !type_.is_empty() => type_,
+        _ => "stdio",
+    }
+}
+
+fn lifecycle_label(config: &McpServerConfig) -> String {
+    match Lifecycle::of(config) {
+        Lifecycle::Always => "always".to_string(),
+        Lifecycle::OnDemand => "on-demand".to_string(),
+        Lifecycle::IdleTimeout { minutes } => format!("idle-timeout {}m", minutes),
+    }
+}
+
+fn servers_list(json: bool) -> Result<(), String> {
+    let servers = save_mcp_config::load_mcp_servers(None)?;
+    if json {
+        return print_json(&servers);
+    }
+    if servers.is_empty() {
+        println!("没有服务器，可以通过 servers import 导入");
+        return Ok(());
+    }
+
+    println!("{:<24} {:<6} {:<8} {:<18} 命令", "名称", "启用", "类型", "生命周期");
+    for config in &servers {
+        let command = match server_type(config) {
+            "sse" => config.base_url.clone().unwrap_or_default(),
+            _ => std::iter::once(config.command.as_str())
+                .chain(config.args.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        println!(
+            "{:<24} {:<6} {:<8} {:<18} {}",
+            config.name,
+            if config.is_active { "是" } else { "否" },
+            server_type(config),
+            lifecycle_label(config),
+            command
+        );
+    }
+    Ok(())
+}
+
+fn servers_set_active(name: &str, active: bool) -> Result<(), String> {
+    save_mcp_config::set_server_active(name, active)?;
+    println!("服务器 {} 已{}", name, if active { "启用" } else { "停用" });
+    Ok(())
+}
+
+// 启动服务器并更新工具列表缓存，check 为 false 时保持运行直到服务器退出
+fn servers_start(name: &str, check: bool) -> Result<(), String> {
+    let config = save_mcp_config::get_mcp_server(name)?;
+    if server_type(&config) == "sse" {
+        return Err(format!("服务器 {} 是 SSE 服务器，不需要在本地启动", name));
+    }
+
+    let client = McpClient::connect(&config, Arc::new(NoopHandler))?;
+    let container_id = config.docker.as_ref()
+        .and_then(|_| docker_runtime::read_container_id(name, CONTAINER_ID_TIMEOUT));
+    let result = client.list_tools()
+        .and_then(|tools| mcp_tool_cache::save_tools(name, &tools))
+        .map(|cached| println!("服务器 {} 已启动，{} 个工具", name, cached.tools.len()));
+
+    if result.is_ok() && !check {
+        // 服务器在独立进程组中运行，收不到终端的 Ctrl+C，收到信号后在下面统一清理
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let flag = interrupted.clone();
+        if let Err(e) = ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst)) {
+            eprintln!("注册 Ctrl+C 处理失败: {}", e);
+        }
+
+        // 记录进程，让 servers stop 可以从其他终端停止服务器
+        save_mcp_config::update_server_process(name, client.pid(), container_id.as_deref())?;
+        println!("按 Ctrl+C 或执行 servers stop {} 停止", name);
+        while client.is_running() && !interrupted.load(Ordering::SeqCst) {
+            std::thread::sleep(WAIT_INTERVAL);
+        }
+        if interrupted.load(Ordering::SeqCst) {
+            println!("正在停止服务器 {}", name);
+        } else {
+            println!("服务器 {} 已退出", name);
+        }
+    }
+
+    client.close();
+    if let Some(id) = &container_id {
+        docker_runtime::remove_container(id)?;
+    }
+    if !check {
+        save_mcp_config::update_server_process(name, None, None)?;
+    }
+    result
+}
+
+// 记录的 pid 可能已被系统分配给其他进程，按命令行确认仍是服务器进程
+fn is_server_process(config: &McpServerConfig, pid: u32) -> bool {
+    let program = match mcp_client::server_command(config) {
+        Ok(command) => std::path::Path::new(command.get_program())
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        Err(_) => return false,
+    };
+    !program.is_empty()
+        && tools::process_command_line(pid).is_some_and(|command_line| command_line.contains(&program))
+}
+
+fn servers_stop(name: &str) -> Result<(), String> {
+    let config = save_mcp_config::get_mcp_server(name)?;
+    let (pid, container_id) = save_mcp_config::recorded_process(name)?;
+    if pid.is_none() && container_id.is_none() {
+        return Err(format!("服务器 {} 没有在运行", name));
+    }
+
+    if let Some(pid) = pid {
+        if is_server_process(&config, pid) {
+            tools::kill_process_group(pid);
+        } else {
+            println!("进程 {} 已不是服务器 {} 的进程，跳过", pid, name);
+        }
+    }
+    if let Some(id) = &container_id {
+        docker_runtime::remove_container(id)?;
+    }
+    save_mcp_config::update_server_process(name, None, None)?;
+    println!("服务器 {} 已停止", name);
+    Ok(())
+}
+
+// 只显示密钥的前几位，避免输出到终端或 CI 日志中
+fn mask_key(key: &str) -> String {
+    if key.is_empty() {
+        return String::new();
+    }
+    format!("{}****", key.chars().take(4).collect::<String>())
+}
+
+fn models_list(json: bool) -> Result<(), String> {
+    let configs: Vec<ModelConfig> = model_config::load_model_configs()?
+        .into_iter()
+        .map(|config| ModelConfig { session_key: mask_key(&config.session_key), ..config })
+        .collect();
+    if json {
+        return print_json(&configs);
+    }
+    if configs.is_empty() {
+        println!("没有模型配置，可以通过 models set 添加");
+        return Ok(());
+    }
+
+    println!("{:<16} {:<28} {:<36} 密钥", "provider", "模型", "地址");
+    for config in &configs {
+        println!("{:<16} {:<28} {:<36} {}", config.provider, config.model, config.api_url, config.session_key);
+    }
+    Ok(())
+}
+
+// 未指定的字段保留原有配置
+fn models_set(provider: &str, args: &Args) -> Result<(), String> {
+    let existing = model_config::load_model_configs()?
+        .into_iter()
+        .find(|config| config.provider == provider);
+
+    let session_key = match args.option("key").as_deref() {
+        Some("-") => read_input("-")?.trim().to_string(),
+        Some(key) => key.to_string(),
+        None => existing.as_ref().map(|config| config.session_key.clone()).unwrap_or_default(),
+    };
+    let config = ModelConfig {
+        provider: provider.to_string(),
+        api_url: args.option("url")
+            .or_else(|| existing.as_ref().map(|config| config.api_url.clone()))
+            .ok_or_else(|| "新建模型配置需要指定 --url".to_string())?,
+        model: args.option("model")
+            .or_else(|| existing.as_ref().map(|config| config.model.clone()))
+            .ok_or_else(|| "新建模型配置需要指定 --model".to_string())?,
+        session_key,
+        endpoint: args.option("endpoint").or_else(|| existing.as_ref().and_then(|config| config.endpoint.clone())),
+        method: args.option("method").or_else(|| existing.as_ref().and_then(|config| config.method.clone())),
+    };
+
+    tauri::async_runtime::block_on(model_config::save_model_config(provider.to_string(), config))?;
+    println!("模型配置 {} 已保存", provider);
+    Ok(())
+}
+
+fn tools_status(json: bool) -> Result<(), String> {
+    if json {
+        return print_json(&tauri::async_runtime::block_on(tools::check_tools_status())?);
+    }
+
+    println!("{:<16} {:<8} {:<16} 路径", "工具", "已安装", "版本");
+    for tool in tools::load_registry()? {
+        let status = tool.status();
+        println!(
+            "{:<16} {:<8} {:<16} {}",
+            tool.id,
+            if status.installed { "是" } else { "否" },
+            status.version.unwrap_or_default(),
+            status.path.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+fn tools_install(ids: &[&str]) -> Result<(), String> {
+    // 下载进度每 10% 输出一次，避免刷屏
+    let last_percent = Cell::new(-1);
+    let print_progress = |tool: &str, progress: InstallProgress| match progress {
+        InstallProgress::Started => {
+            last_percent.set(-1);
+            println!("{}: 开始安装", tool);
+        },
+        InstallProgress::Download { percent: Some(percent), .. } => {
+            let step = percent as i32 / 10 * 10;
+            if step > last_percent.get() {
+                last_percent.set(step);
+                println!("{}: 下载 {}%", tool, step);
+            }
+        },
+        InstallProgress::Extract => println!("{}: 解压", tool),
+        InstallProgress::Install => println!("{}: 安装", tool),
+        InstallProgress::Verify => println!("{}: 验证", tool),
+        InstallProgress::Log { message } => println!("{}: {}", tool, message),
+        InstallProgress::Done { version } => println!("{}: 安装完成 {}", tool, version.unwrap_or_default()),
+        // 失败原因由返回的错误输出
+        _ => {},
+    };
+    let cancel = AtomicBool::new(false);
+
+    for id in ids {
+        let tool = tools::find_tool(id)?;
+        if tool.check_installed() {
+            println!("{} 已安装，跳过", tool.id);
+            continue;
+        }
+        tools::install_tool(&tool, &print_progress, &cancel)?;
+    }
+    Ok(())
+}
+
+fn run_doctor(json: bool) -> Result<(), String> {
+    let report = doctor::run_doctor()?;
+    if json {
+        print_json(&report)?;
+    } else {
+        println!("工具:");
+        for tool in &report.tools {
+            match (&tool.path, &tool.version) {
+                (Some(path), version) => println!(
+                    "  [✓] {} {} ({})", tool.name, version.as_deref().unwrap_or("未知版本"), path
+                ),
+                (None, _) => println!("  [✗] {} 未安装", tool.name),
+            }
+            for suggestion in &tool.suggestions {
+                println!("      - {}", suggestion);
+            }
+        }
+
+        println!("代理:");
+        let proxy = &report.proxy;
+        for (name, value) in [
+            ("HTTP_PROXY", &proxy.http_proxy),
+            ("HTTPS_PROXY", &proxy.https_proxy),
+            ("ALL_PROXY", &proxy.all_proxy),
+            ("NO_PROXY", &proxy.no_proxy),
+        ] {
+            println!("  {}: {}", name, value.as_deref().unwrap_or("未设置"));
+        }
+        for suggestion in &proxy.suggestions {
+            println!("      - {}", suggestion);
+        }
+
+        println!("目录:");
+        for directory in &report.directories {
+            println!(
+                "  [{}] {} {}",
+                if directory.writable { "✓" } else { "✗" },
+                directory.name,
+                directory.path
+            );
+            if let Some(error) = &directory.error {
+                println!("      - {}", error);
+            }
+            for suggestion in &directory.suggestions {
+                println!("      - {}", suggestion);
+            }
+        }
+    }
+
+    let unwritable = report.directories.iter().filter(|directory| !directory.writable).count();
+    if unwritable > 0 {
+        return Err(format!("{} 个目录不可写", unwritable));
+    }
+    Ok(())
+}
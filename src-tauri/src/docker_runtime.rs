@@ -0,0 +1,275 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::save_mcp_config::McpServerConfig;
+use crate::tools;
+
+// 应用启动的容器都带有这个标签，用于清理遗留的容器
+pub const MANAGED_LABEL: &str = "omni-mcp.managed=true";
+// 启动容器的进程 pid，应用和命令行工具可能同时运行，只清理启动进程已退出的容器
+pub const OWNER_LABEL: &str = "omni-mcp.owner";
+
+// 挂载到容器中的目录
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DockerMount {
+    // 主机路径，支持 ~ 开头
+    pub source: String,
+    pub target: String,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+// docker 类型服务器的容器设置，环境变量使用服务器配置中的 env
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DockerConfig {
+    pub image: String,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub mounts: Vec<DockerMount>,
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+impl DockerConfig {
+    // 完整的镜像名，如 mcp/fetch:latest
+    pub fn image_ref(&self) -> String {
+        match &self.tag {
+            Some(tag) if !tag.is_empty() => format!("{}:{}", self.image, tag),
+            _ => self.image.clone(),
+        }
+    }
+}
+
+fn docker_program() -> PathBuf {
+    tools::resolve_command("docker")
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => format!("{}{}", home.to_string_lossy(), rest),
+        _ => path.to_string(),
+    }
+}
+
+// docker run 写入容器 id 的文件
+pub fn cid_file(server_name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("omni-mcp-{}.cid", server_name))
+}
+
+// 构造以 stdio 方式运行容器的命令
+//
+// 环境变量只以 -e KEY 的形式传给 docker，值通过 docker 进程的环境变量传递，
+// 避免出现在进程列表中
+pub fn docker_command(config: &McpServerConfig, docker: &DockerConfig) -> Result<Command, String> {
+    if docker.image.is_empty() {
+        return Err(format!("服务器 {} 没有设置镜像", config.name));
+    }
+
+    // docker run 要求 cidfile 不存在
+    let cid_path = cid_file(&config.name);
+    if cid_path.exists() {
+        std::fs::remove_file(&cid_path).map_err(|e| format!("删除 {:?} 失败: {}", cid_path, e))?;
+    }
+
+    let mut command = Command::new(docker_program());
+    command.args(["run", "-i", "--rm", "--label", MANAGED_LABEL])
+        .arg("--label").arg(format!("omni-mcp.server={}", config.name))
+        .arg("--label").arg(format!("{}={}", OWNER_LABEL, std::process::id()))
+        .arg("--cidfile").arg(&cid_path);
+
+    if let Some(network) = docker.network.as_deref().filter(|network| !network.is_empty()) {
+        command.args(["--network", network]);
+    }
+    for mount in &docker.mounts {
+        let mut volume = format!("{}:{}", expand_home(&mount.source), mount.target);
+        if mount.read_only {
+            volume.push_str(":ro");
+        }
+        command.arg("-v").arg(volume);
+    }
+    if let Some(env) = config.env.as_ref().and_then(|env| env.as_object()) {
+        for key in env.keys() {
+            command.arg("-e").arg(key);
+        }
+    }
+
+    command.arg(docker.image_ref()).args(&config.args);
+    Ok(command)
+}
+
+// 等待 docker run 写入容器 id
+pub fn read_container_id(server_name: &str, timeout: Duration) -> Option<String> {
+    let path = cid_file(server_name);
+    let started = Instant::now();
+    while started.elapsed() < timeout {
+        if let Ok(id) = std::fs::read_to_string(&path) {
+            let id = id.trim().to_string();
+            if !id.is_empty() {
+                return Some(id);
+            }
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    None
+}
+
+// 强制删除容器
+pub fn remove_container(id: &str) -> Result<(), String> {
+    let output = Command::new(docker_program())
+        .args(["rm", "-f", id])
+        .output()
+        .map_err(|e| format!("执行 docker rm 失败: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // 使用 --rm 启动的容器可能已经被删除
+        if !stderr.contains("No such container") {
+            return Err(format!("删除容器 {} 失败: {}", id, stderr.trim()));
+        }
+    }
+    Ok(())
+}
+
+// 删除遗留的容器：带有应用标签且启动进程已经退出的容器
+//
+// 没有 owner 标签的旧容器只在数据库中有记录时删除，owner 仍在运行的容器即使有记录也保留
+pub fn cleanup_orphaned_containers(recorded: &[String]) -> usize {
+    let docker_installed = tools::find_tool("docker").is_ok_and(|docker| docker.check_installed());
+    if !docker_installed {
+        return 0;
+    }
+
+    let format = format!("{{{{.ID}}}}\t{{{{.Label \"{}\"}}}}", OWNER_LABEL);
+    let output = match Command::new(docker_program())
+        .args(["ps", "-a", "--filter", &format!("label={}", MANAGED_LABEL), "--format", &format])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            warn!("查询遗留容器失败: {}", String::from_utf8_lossy(&output.stderr).trim());
+            return 0;
+        },
+        Err(e) => {
+            warn!("查询遗留容器失败: {}", e);
+            return 0;
+        },
+    };
+
+    let mut ids: Vec<String> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let (id, owner) = line.split_once('\t').unwrap_or((line, ""));
+        let id = id.trim();
+        if id.is_empty() {
+            continue;
+        }
+        let orphaned = match owner.trim().parse::<u32>() {
+            Ok(pid) => !tools::process_alive(pid),
+            // docker ps 输出短 id，数据库中记录的是完整 id
+            Err(_) => recorded.iter().any(|known| known.starts_with(id)),
+        };
+        if orphaned {
+            ids.push(id.to_string());
+        }
+    }
+
+    let mut removed = 0;
+    for id in &ids {
+        match remove_container(id) {
+            Ok(_) => removed += 1,
+            Err(e) => warn!("{}", e),
+        }
+    }
+    if removed > 0 {
+        info!("已清理 {} 个遗留容器", removed);
+    }
+    removed
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_env::TestEnv;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    fn test_config() -> McpServerConfig {
+        serde_json::from_value(serde_json::json!({
+            "name": "fetch",
+            "command": "docker",
+            "args": ["--verbose"],
+            "isActive": true,
+            "env": { "API_KEY": "secret" },
+        }))
+        .unwrap()
+    }
+
+    // 写一个记录参数的假 docker，ps 输出 ps_output
+    fn write_fake_docker(dir: &Path, ps_output: &str) -> PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        let log = dir.join("docker.log");
+        let ps_file = dir.join("ps.txt");
+        std::fs::write(&ps_file, ps_output).unwrap();
+        let script = dir.join("docker");
+        std::fs::write(&script, format!(
+            "#!/bin/sh\necho \"$@\" >> '{}'\nif [ \"$1\" = ps ]; then cat '{}'; fi\n",
+            log.display(),
+            ps_file.display()
+        )).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        log
+    }
+
+    #[test]
+    fn docker_command_runs_labelled_stdio_container() {
+        // docker 的路径来自工具注册表，注册表依赖 HOME
+        let _env = TestEnv::lock();
+        let config = test_config();
+        let docker = DockerConfig {
+            image: "mcp/fetch".to_string(),
+            tag: Some("latest".to_string()),
+            ..Default::default()
+        };
+        let command = docker_command(&config, &docker).unwrap();
+        let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+
+        assert_eq!(args[..5], ["run", "-i", "--rm", "--label", MANAGED_LABEL]);
+        let has_pair = |name: &str, value: &str| args.windows(2).any(|pair| pair[0] == name && pair[1] == value);
+        assert!(has_pair("--label", "omni-mcp.server=fetch"));
+        assert!(has_pair("--label", &format!("{}={}", OWNER_LABEL, std::process::id())));
+        assert!(has_pair("--cidfile", &cid_file("fetch").to_string_lossy()));
+        // 环境变量的值不出现在参数中
+        assert!(has_pair("-e", "API_KEY"));
+        assert!(!args.iter().any(|arg| arg.contains("secret")));
+        assert_eq!(args[args.len() - 2..], ["mcp/fetch:latest", "--verbose"]);
+    }
+
+    #[test]
+    fn cleanup_removes_only_orphaned_containers() {
+        let mut env = TestEnv::lock();
+        let dir = std::env::temp_dir().join(format!("omni-mcp-docker-test-{}", std::process::id()));
+        // 超过 pid 上限的 pid 不可能在运行
+        let ps_output = format!(
+            "dead\t99999999\nalive\t{}\nrecorded\t\nunknown\t\n",
+            std::process::id()
+        );
+        let log = write_fake_docker(&dir, &ps_output);
+
+        env.prepend_path(&dir);
+        let removed = cleanup_orphaned_containers(&["recorded0123456789".to_string()]);
+
+        let calls = std::fs::read_to_string(&log).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(removed, 2);
+        assert!(calls.contains(&format!("ps -a --filter label={}", MANAGED_LABEL)));
+        assert!(calls.contains("rm -f dead"));
+        assert!(calls.contains("rm -f recorded"));
+        assert!(!calls.contains("rm -f alive"));
+        assert!(!calls.contains("rm -f unknown"));
+    }
+}
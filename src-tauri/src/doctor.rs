@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::Serialize;
+
+use crate::save_mcp_config::get_mcp_servers_root_dir;
+use crate::sqlite_db::app_data_dir;
+use crate::tools;
+
+// 单个工具的诊断结果
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolDiagnosis {
+    pub id: String,
+    pub name: String,
+    pub installed: bool,
+    pub path: Option<String>,
+    pub version: Option<String>,
+    // PATH 中找到的所有同名可执行文件
+    pub path_entries: Vec<String>,
+    pub suggestions: Vec<String>,
+}
+
+// 代理设置
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyDiagnosis {
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub all_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+// 目录可写性检查结果
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryDiagnosis {
+    pub name: String,
+    pub path: String,
+    pub writable: bool,
+    pub error: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorReport {
+    pub tools: Vec<ToolDiagnosis>,
+    pub proxy: ProxyDiagnosis,
+    pub directories: Vec<DirectoryDiagnosis>,
+}
+
+// 读取环境变量，同时兼容大小写两种写法
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .or_else(|_| std::env::var(name.to_lowercase()))
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+fn diagnose_tool(tool: &tools::ToolSpec) -> ToolDiagnosis {
+    let status = tool.status();
+    let path_entries: Vec<PathBuf> = tool.find_all_in_path();
+    let mut suggestions = Vec::new();
+
+    match &status.path {
+        None => suggestions.push(tool.install_hint()),
+        Some(path) => {
+            if status.version.is_none() {
+                suggestions.push(format!("无法获取 {} 的版本号，{} 可能已损坏，建议重新安装", tool.name, path));
+            }
+
+            let path = Path::new(path);
+            if path_entries.is_empty() {
+                // 托管目录中的工具不在 PATH 中时，依赖该命令启动的 MCP 服务器会找不到它
+                if let Some(dir) = path.parent() {
+                    suggestions.push(format!("{} 不在 PATH 中，请将 {} 添加到 PATH", tool.name, dir.to_string_lossy()));
+                }
+            } else if path_entries.len() > 1 {
+                suggestions.push(format!(
+                    "PATH 中存在 {} 个 {}，MCP 服务器将使用 {}，如非预期请调整 PATH 顺序或删除多余的版本",
+                    path_entries.len(),
+                    tool.name,
+                    path_entries[0].to_string_lossy()
+                ));
+            } else if path_entries[0] != path {
+                suggestions.push(format!(
+                    "检测到的 {} ({}) 与 PATH 中的 {} 不同，MCP 服务器将使用 PATH 中的版本",
+                    tool.name,
+                    path.to_string_lossy(),
+                    path_entries[0].to_string_lossy()
+                ));
+            }
+        },
+    }
+
+    ToolDiagnosis {
+        id: tool.id.clone(),
+        name: tool.name.clone(),
+        installed: status.installed,
+        path: status.path,
+        version: status.version,
+        path_entries: path_entries.iter().map(|path| path.to_string_lossy().to_string()).collect(),
+        suggestions,
+    }
+}
+
+fn diagnose_proxy() -> ProxyDiagnosis {
+    let http_proxy = env_var("HTTP_PROXY");
+    let https_proxy = env_var("HTTPS_PROXY");
+    let all_proxy = env_var("ALL_PROXY");
+    let no_proxy = env_var("NO_PROXY");
+    let mut suggestions = Vec::new();
+
+    // 工具安装脚本的下载都走 HTTPS
+    if https_proxy.is_none() && (http_proxy.is_some() || all_proxy.is_some()) {
+        suggestions.push("只设置了 HTTP_PROXY 或 ALL_PROXY，工具版本查询只读取 HTTPS_PROXY，建议同时设置 HTTPS_PROXY".to_string());
+    }
+    for (name, value) in [("HTTP_PROXY", &http_proxy), ("HTTPS_PROXY", &https_proxy), ("ALL_PROXY", &all_proxy)] {
+        if let Some(value) = value {
+            if !value.contains("://") {
+                suggestions.push(format!("{} 缺少协议前缀，建议写成 http://{}", name, value));
+            }
+        }
+    }
+
+    ProxyDiagnosis {
+        http_proxy,
+        https_proxy,
+        all_proxy,
+        no_proxy,
+        suggestions,
+    }
+}
+
+// 通过创建并删除临时文件检查目录是否可写
+fn diagnose_directory(name: &str, dir: Result<PathBuf, String>) -> DirectoryDiagnosis {
+    let dir = match dir {
+        Ok(dir) => dir,
+        Err(e) => {
+            return DirectoryDiagnosis {
+                name: name.to_string(),
+                path: String::new(),
+                writable: false,
+                error: Some(e),
+                suggestions: vec!["请检查 HOME 环境变量是否正确设置".to_string()],
+            };
+        }
+    };
+
+    let probe_file = dir.join(".omni-doctor-write-test");
+    let result = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(&probe_file, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe_file));
+
+    let (writable, error, suggestions) = match result {
+        Ok(_) => (true, None, Vec::new()),
+        Err(e) => (
+            false,
+            Some(e.to_string()),
+            vec![format!("请检查 {} 的权限，确保当前用户可以写入", dir.to_string_lossy())],
+        ),
+    };
+
+    DirectoryDiagnosis {
+        name: name.to_string(),
+        path: dir.to_string_lossy().to_string(),
+        writable,
+        error,
+        suggestions,
+    }
+}
+
+// 生成诊断报告
+pub fn run_doctor() -> Result<DoctorReport, String> {
+    info!("开始环境诊断");
+
+    let tools = tools::load_registry()?
+        .iter()
+        .map(diagnose_tool)
+        .collect();
+
+    let directories = vec![
+        diagnose_directory("数据目录", app_data_dir().ok_or_else(|| "无法获取应用数据目录".to_string())),
+        diagnose_directory("MCP 服务器目录", get_mcp_servers_root_dir()),
+        diagnose_directory("工具目录", tools::app_bin_dir()),
+    ];
+
+    Ok(DoctorReport {
+        tools,
+        proxy: diagnose_proxy(),
+        directories,
+    })
+}
+
+// 环境诊断：工具路径和版本、PATH 冲突、代理设置以及目录是否可写
+#[tauri::command]
+pub async fn doctor() -> Result<DoctorReport, String> {
+    run_doctor()
+}
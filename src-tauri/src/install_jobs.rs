@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::tools::{self, InstallProgress, UninstallReport, UpgradeReport};
+
+// 前端监听的安装进度事件
+pub const INSTALL_PROGRESS_EVENT: &str = "tool-install-progress";
+
+// 安装进度事件内容
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct InstallProgressEvent {
+    job_id: String,
+    // 正在安装的工具，安装依赖时与任务的工具不同
+    tool: String,
+    #[serde(flatten)]
+    progress: InstallProgress,
+}
+
+struct InstallJob {
+    tool: String,
+    cancel: Arc<AtomicBool>,
+}
+
+// 正在运行的安装任务
+#[derive(Default)]
+pub struct InstallJobs {
+    jobs: Mutex<HashMap<String, InstallJob>>,
+    next_id: AtomicU64,
+}
+
+// 构造向前端发送进度事件的回调
+fn progress_emitter(app: AppHandle, job_id: String) -> impl Fn(&str, InstallProgress) {
+    move |tool_id: &str, progress: InstallProgress| {
+        let event = InstallProgressEvent {
+            job_id: job_id.clone(),
+            tool: tool_id.to_string(),
+            progress,
+        };
+        if let Err(e) = app.emit(INSTALL_PROGRESS_EVENT, event) {
+            warn!("发送安装进度事件失败: {}", e);
+        }
+    }
+}
+
+impl InstallJobs {
+    // 登记任务，同一个工具同时只能有一个安装或升级任务
+    fn register(&self, tool: &tools::ToolSpec) -> Result<(String, Arc<AtomicBool>), String> {
+        let mut jobs = self.jobs.lock().map_err(|e| format!("获取安装任务失败: {}", e))?;
+        if jobs.values().any(|job| job.tool == tool.id) {
+            return Err(format!("{} 正在安装中", tool.name));
+        }
+
+        let job_id = format!("install-{}", self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+        let cancel = Arc::new(AtomicBool::new(false));
+        jobs.insert(job_id.clone(), InstallJob {
+            tool: tool.id.clone(),
+            cancel: cancel.clone(),
+        });
+        Ok((job_id, cancel))
+    }
+
+    // 任务结束后移除
+    fn finish(&self, job_id: &str) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.remove(job_id);
+        }
+    }
+
+    // 在后台线程中启动安装任务，返回任务 id
+    pub fn start(&self, app: AppHandle, tool: tools::ToolSpec) -> Result<String, String> {
+        let (job_id, cancel) = self.register(&tool)?;
+
+        info!("启动安装任务 {}: {}", job_id, tool.id);
+        let thread_job_id = job_id.clone();
+        std::thread::spawn(move || {
+            let emit = progress_emitter(app.clone(), thread_job_id.clone());
+
+            // 失败和取消都已经通过进度事件通知前端，这里只记录日志
+            if let Err(e) = tools::install_tool(&tool, &emit, &cancel) {
+                warn!("安装任务 {} 结束: {}", thread_job_id, e);
+            }
+
+            app.state::<InstallJobs>().finish(&thread_job_id);
+        });
+
+        Ok(job_id)
+    }
+
+    // 取消安装任务
+    pub fn cancel(&self, job_id: &str) -> Result<(), String> {
+        let jobs = self.jobs.lock().map_err(|e| format!("获取安装任务失败: {}", e))?;
+        let job = jobs.get(job_id).ok_or_else(|| format!("安装任务不存在: {}", job_id))?;
+        job.cancel.store(true, Ordering::SeqCst);
+        info!("取消安装任务 {}: {}", job_id, job.tool);
+        Ok(())
+    }
+}
+
+// 安装单个工具的命令，接受任意已注册的工具 id，返回任务 id
+//
+// 安装在后台进行，进度通过 tool-install-progress 事件通知前端
+#[tauri::command]
+pub fn install_single_tool(app: AppHandle, jobs: State<'_, InstallJobs>, tool: &str) -> Result<String, String> {
+    info!("开始安装单个工具: {}", tool);
+
+    let tool = tools::find_tool(tool)?;
+    jobs.start(app, tool)
+}
+
+// 取消正在进行的安装任务
+#[tauri::command]
+pub fn cancel_tool_install(jobs: State<'_, InstallJobs>, job_id: &str) -> Result<(), String> {
+    jobs.cancel(job_id)
+}
+
+// 将应用安装的工具升级或回退到指定版本，未指定版本时使用注册表中的最新或固定版本
+//
+// 升级同样发送 tool-install-progress 事件，可以通过 cancel_tool_install 取消，失败时恢复原版本
+#[tauri::command]
+pub async fn upgrade_tool(app: AppHandle, tool: String, version: Option<String>) -> Result<UpgradeReport, String> {
+    info!("开始升级工具: {} {:?}", tool, version);
+
+    let tool = tools::find_tool(&tool)?;
+    let (job_id, cancel) = app.state::<InstallJobs>().register(&tool)?;
+
+    let emit = progress_emitter(app.clone(), job_id.clone());
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        tools::upgrade_tool(&tool, version.as_deref(), &emit, &cancel)
+    })
+    .await
+    .map_err(|e| format!("升级任务异常结束: {}", e));
+
+    app.state::<InstallJobs>().finish(&job_id);
+    result?
+}
+
+// 卸载应用安装的工具，不会删除系统包管理器安装的版本
+#[tauri::command]
+pub async fn uninstall_tool(tool: String) -> Result<UninstallReport, String> {
+    info!("开始卸载工具: {}", tool);
+
+    let tool = tools::find_tool(&tool)?;
+    tauri::async_runtime::spawn_blocking(move || tools::uninstall_tool(&tool))
+        .await
+        .map_err(|e| format!("卸载任务异常结束: {}", e))?
+}
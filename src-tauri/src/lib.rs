@@ -1,9 +1,43 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-use log::{info, error};
+use log::{info, warn, error};
 
 // 导入工具模块
 mod tools;
+mod install_jobs;
 mod commands;
+mod doctor;
+mod bundle;
+pub mod cli;
+mod docker_runtime;
+mod local_api;
+mod local_http;
+mod mcp_catalog;
+mod mcp_client;
+mod mcp_elicitation;
+pub mod mcp_gateway;
+mod mcp_health;
+mod mcp_installer;
+mod mcp_lifecycle;
+mod mcp_prompts;
+mod mcp_resources;
+mod mcp_sampling;
+mod mcp_server_tools;
+mod mcp_supervisor;
+mod mcp_tool_cache;
+mod mcp_tool_calls;
+mod package_installer;
+mod pending_requests;
+mod github_handler;
+mod model_config;
+mod save_mcp_config;
+mod sqlite_db;
+mod workspace_roots;
+
+#[cfg(test)]
+mod test_env;
+
+use github_handler::open_github_link;
+use tauri::{Manager, RunEvent};
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -11,25 +45,126 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+// 启动时只检测工具，安装由用户在工具管理页面触发
+fn detect_tools() {
+    match tools::load_registry() {
+        Ok(registry) => {
+            for tool in registry {
+                let status = tool.status();
+                if status.installed {
+                    info!("{} 已安装: {} ({})",
+                        tool.id,
+                        status.version.unwrap_or_default(),
+                        status.path.unwrap_or_default());
+                } else {
+                    warn!("{} 未安装", tool.id);
+                }
+            }
+            info!("工具检测完成");
+        },
+        Err(e) => error!("工具检测失败: {}", e),
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     env_logger::init();
     info!("应用启动");
     
-    // 检查并安装工具
-    match tools::check_and_install_tools() {
-        Ok(_) => info!("工具检查完成"),
-        Err(e) => error!("工具检查失败: {}", e),
-    }
+    // 检测工具需要执行多个外部命令，放到后台线程避免拖慢启动
+    std::thread::spawn(detect_tools);
+    // 清理上次异常退出时遗留的 docker 容器
+    std::thread::spawn(mcp_supervisor::cleanup_orphans);
     
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .manage(install_jobs::InstallJobs::default())
+        .manage(mcp_supervisor::McpSupervisor::default())
+        .manage(mcp_sampling::SamplingRequests::default())
+        .manage(mcp_elicitation::ElicitationRequests::default())
+        .manage(mcp_tool_calls::ToolCalls::default())
+        .manage(mcp_health::HealthMonitor::default())
+        .manage(mcp_gateway::McpGateway::default())
+        .manage(mcp_gateway::ToolCallApprovals::default())
+        .manage(local_api::LocalApi::default())
+        .setup(|app| {
+            // 定期检查运行中服务器的健康状态
+            mcp_health::spawn_monitor(app.handle().clone());
+            // 启动常驻服务器，停止空闲超时的服务器
+            mcp_lifecycle::spawn_lifecycle(app.handle().clone());
+            mcp_gateway::start_if_enabled(app.handle());
+            local_api::start_if_enabled(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
-            commands::execute_command,
-            tools::install_single_tool,
             tools::check_tools_status,
+            install_jobs::install_single_tool,
+            install_jobs::cancel_tool_install,
+            install_jobs::upgrade_tool,
+            install_jobs::uninstall_tool,
+            tools::check_and_install_tools,
+            doctor::doctor,
+            bundle::export_offline_bundle,
+            bundle::import_offline_bundle,
+            commands::execute_command,
+            open_github_link,
+            model_config::get_model_config,
+            model_config::save_model_config,
+            model_config::get_custom_configs,
+            model_config::delete_model_config,
+            save_mcp_config::parse_mcp_config,
+            save_mcp_config::get_all_mcp_servers,
+            mcp_installer::install_mcp_server_from_git,
+            mcp_installer::check_mcp_server_update,
+            mcp_installer::upgrade_mcp_server,
+            package_installer::install_mcp_server_from_package,
+            package_installer::pin_mcp_server_package,
+            mcp_supervisor::start_mcp_server,
+            mcp_supervisor::stop_mcp_server,
+            mcp_supervisor::list_running_mcp_servers,
+            mcp_health::get_mcp_servers_status,
+            mcp_lifecycle::set_mcp_server_lifecycle,
+            mcp_gateway::get_mcp_gateway_status,
+            mcp_gateway::set_mcp_gateway,
+            mcp_gateway::reset_mcp_gateway_token,
+            mcp_gateway::respond_gateway_tool_call,
+            local_api::get_local_api_status,
+            local_api::set_local_api,
+            local_api::reset_local_api_token,
+            mcp_catalog::search_mcp_catalog,
+            mcp_catalog::install_mcp_catalog_server,
+            mcp_catalog::update_mcp_catalog,
+            mcp_resources::list_mcp_resources,
+            mcp_resources::list_mcp_resource_templates,
+            mcp_resources::read_mcp_resource,
+            mcp_resources::subscribe_mcp_resource,
+            mcp_resources::unsubscribe_mcp_resource,
+            mcp_resources::attach_mcp_resource,
+            mcp_prompts::list_mcp_prompts,
+            mcp_prompts::get_mcp_prompt,
+            mcp_sampling::respond_sampling_request,
+            mcp_elicitation::respond_elicitation_request,
+            mcp_server_tools::get_server_tools,
+            mcp_server_tools::set_server_tool,
+            mcp_server_tools::get_agent_tools,
+            mcp_server_tools::call_agent_tool,
+            mcp_tool_cache::get_cached_mcp_tools,
+            mcp_tool_cache::refresh_mcp_tools,
+            mcp_tool_calls::call_mcp_tool,
+            mcp_tool_calls::cancel_tool_call,
+            workspace_roots::get_workspace_profiles,
+            workspace_roots::pick_workspace_roots,
+            workspace_roots::remove_workspace_root,
+            workspace_roots::set_workspace_profile,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // 退出前停止应用启动的服务器和容器
+            if let RunEvent::Exit = event {
+                app.state::<mcp_supervisor::McpSupervisor>().stop_all();
+            }
+        });
 }
@@ -0,0 +1,281 @@
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::async_runtime::block_on;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::local_http::{self, HttpRequest, LocalServer};
+use crate::model_config::{self, ModelConfig};
+use crate::save_mcp_config;
+use crate::sqlite_db::Database;
+use crate::tools;
+
+// 本地 API 默认端口，与 MCP 网关的端口相邻
+pub const DEFAULT_API_PORT: u16 = 17322;
+// 通过本地 API 修改数据后发给前端的事件，前端据此重新加载
+pub const API_DATA_CHANGED_EVENT: &str = "local-api-data-changed";
+// app_settings 中的本地 API 设置
+const API_ENABLED_KEY: &str = "api_enabled";
+const API_PORT_KEY: &str = "api_port";
+const API_TOKEN_KEY: &str = "api_token";
+
+// 本地 API 设置和运行状态
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalApiStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    pub url: String,
+    // 请求需要在 Authorization: Bearer 头中携带的令牌
+    pub token: String,
+}
+
+// 供脚本调用的 REST/JSON API，提供与 Tauri 命令相同的操作
+//
+// 只监听 127.0.0.1，每个请求都需要携带令牌，读写与应用相同的数据库
+#[derive(Default)]
+pub struct LocalApi {
+    server: Mutex<Option<LocalServer>>,
+    token: Mutex<String>,
+}
+
+// 请求失败时的 HTTP 状态码和错误信息
+type ApiError = (u16, String);
+
+fn get_db() -> Result<Database, String> {
+    let db = Database::new().map_err(|e| format!("初始化数据库失败: {}", e))?;
+    db.init_app_settings_table().map_err(|e| format!("初始化表失败: {}", e))?;
+    Ok(db)
+}
+
+// 读取设置，还没有令牌时生成并保存
+fn load_settings() -> Result<(bool, u16, String), String> {
+    let db = get_db()?;
+    let enabled = db.get_setting(API_ENABLED_KEY).is_some_and(|value| value == "true");
+    let port = db.get_setting(API_PORT_KEY)
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_API_PORT);
+    let token = match db.get_setting(API_TOKEN_KEY).filter(|token| !token.is_empty()) {
+        Some(token) => token,
+        None => {
+            let token = local_http::generate_token();
+            db.set_setting(API_TOKEN_KEY, &token).map_err(|e| format!("保存令牌失败: {}", e))?;
+            token
+        },
+    };
+    Ok((enabled, port, token))
+}
+
+fn internal_error(message: String) -> ApiError {
+    (500, message)
+}
+
+fn bad_request(message: String) -> ApiError {
+    (400, message)
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, ApiError> {
+    serde_json::to_value(value).map_err(|e| internal_error(format!("序列化结果失败: {}", e)))
+}
+
+// 通知前端重新加载通过 API 修改的数据
+fn notify_changed(app: &AppHandle, resource: &str) {
+    if let Err(e) = app.emit(API_DATA_CHANGED_EVENT, json!({ "resource": resource })) {
+        warn!("发送 {} 事件失败: {}", API_DATA_CHANGED_EVENT, e);
+    }
+}
+
+// 导入请求体可以是 {"config": "..."}，也可以直接是 mcpServers 配置
+fn import_config(request: &HttpRequest) -> Result<String, ApiError> {
+    let body = String::from_utf8(request.body.clone()).map_err(|e| bad_request(format!("请求体不是有效的 UTF-8: {}", e)))?;
+    let wrapped = serde_json::from_str::<Value>(&body).ok()
+        .and_then(|value| value.get("config").and_then(|config| config.as_str()).map(String::from));
+    Ok(wrapped.unwrap_or(body))
+}
+
+// 路径中的 provider 优先于请求体中的 provider
+fn model_config_body(request: &HttpRequest, provider: &str) -> Result<ModelConfig, ApiError> {
+    let mut body = request.json().map_err(bad_request)?;
+    let object = body.as_object_mut().ok_or_else(|| bad_request("请求体必须是 JSON 对象".to_string()))?;
+    object.insert("provider".to_string(), json!(provider));
+    serde_json::from_value(body).map_err(|e| bad_request(format!("模型配置无效: {}", e)))
+}
+
+fn route(app: &AppHandle, request: &HttpRequest) -> Result<Value, ApiError> {
+    let segments: Vec<String> = request.path.trim_matches('/').split('/').map(local_http::percent_decode).collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        // get_all_mcp_servers
+        ("GET", ["api", "servers"]) => {
+            let is_active = match request.query.get("isActive").map(String::as_str) {
+                None | Some("") => None,
+                Some("true") => Some(true),
+                Some("false") => Some(false),
+                Some(other) => return Err(bad_request(format!("isActive 只能是 true 或 false: {}", other))),
+            };
+            to_json(block_on(save_mcp_config::get_all_mcp_servers(is_active)).map_err(internal_error)?)
+        },
+        // parse_mcp_config
+        ("POST", ["api", "servers", "import"]) => {
+            let config = import_config(request)?;
+            let message = save_mcp_config::parse_mcp_config(&config).map_err(bad_request)?;
+            notify_changed(app, "servers");
+            Ok(json!({ "message": message }))
+        },
+        // get_custom_configs
+        ("GET", ["api", "models"]) => {
+            let filter_type = request.query.get("filterType").cloned();
+            to_json(block_on(model_config::get_custom_configs(filter_type)).map_err(internal_error)?)
+        },
+        // get_model_config
+        ("GET", ["api", "models", provider]) => {
+            match block_on(model_config::get_model_config(provider.to_string())).map_err(internal_error)? {
+                Some(config) => to_json(config),
+                None => Err((404, format!("模型配置不存在: {}", provider))),
+            }
+        },
+        // save_model_config
+        ("PUT", ["api", "models", provider]) => {
+            let config = model_config_body(request, provider)?;
+            block_on(model_config::save_model_config(provider.to_string(), config.clone())).map_err(internal_error)?;
+            notify_changed(app, "models");
+            to_json(config)
+        },
+        // delete_model_config
+        ("DELETE", ["api", "models", provider]) => {
+            block_on(model_config::delete_model_config(provider.to_string())).map_err(internal_error)?;
+            notify_changed(app, "models");
+            Ok(json!({ "deleted": provider }))
+        },
+        // check_tools_status
+        ("GET", ["api", "tools", "status"]) => block_on(tools::check_tools_status()).map_err(internal_error),
+        (_, ["api", "servers"] | ["api", "servers", "import"] | ["api", "models", ..] | ["api", "tools", "status"]) => {
+            Err((405, format!("不支持的请求方法: {}", request.method)))
+        },
+        _ => Err((404, format!("接口不存在: {}", request.path))),
+    }
+}
+
+fn handle_request(app: &AppHandle, request: HttpRequest, mut stream: TcpStream) {
+    let token = app.state::<LocalApi>().token();
+    if !local_http::is_authorized(&request, &token) {
+        let body = serde_json::to_vec(&json!({ "error": "缺少或无效的令牌" })).unwrap_or_default();
+        let _ = local_http::write_response(&mut stream, 401, "application/json", &[("WWW-Authenticate", "Bearer")], &body);
+        return;
+    }
+
+    let (status, body) = match route(app, &request) {
+        Ok(body) => (200, body),
+        Err((status, message)) => {
+            warn!("本地 API {} {} 失败: {}", request.method, request.path, message);
+            (status, json!({ "error": message }))
+        },
+    };
+    let _ = local_http::write_json(&mut stream, status, &body);
+}
+
+impl LocalApi {
+    pub fn start(&self, app: &AppHandle, port: u16, token: String) -> Result<(), String> {
+        self.set_token(token);
+        let mut server = self.server.lock().map_err(|e| format!("获取本地 API 状态失败: {}", e))?;
+        if server.as_ref().is_some_and(|server| server.addr.port() == port) {
+            return Ok(());
+        }
+        // 端口变化时先停止旧的监听
+        server.take();
+
+        let app = app.clone();
+        let handler: local_http::HttpHandler = Arc::new(move |request, stream| handle_request(&app, request, stream));
+        *server = Some(LocalServer::bind("本地 API", port, handler)?);
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Ok(mut server) = self.server.lock() {
+            server.take();
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.server.lock().is_ok_and(|server| server.is_some())
+    }
+
+    fn token(&self) -> String {
+        self.token.lock().map(|token| token.clone()).unwrap_or_default()
+    }
+
+    fn set_token(&self, token: String) {
+        if let Ok(mut current) = self.token.lock() {
+            *current = token;
+        }
+    }
+}
+
+fn status(api: &LocalApi) -> Result<LocalApiStatus, String> {
+    let (enabled, port, token) = load_settings()?;
+    Ok(LocalApiStatus {
+        enabled,
+        running: api.is_running(),
+        port,
+        url: format!("http://127.0.0.1:{}/api", port),
+        token,
+    })
+}
+
+// 应用启动时按设置启动本地 API
+pub fn start_if_enabled(app: &AppHandle) {
+    match load_settings() {
+        Ok((true, port, token)) => {
+            if let Err(e) = app.state::<LocalApi>().start(app, port, token) {
+                warn!("启动本地 API 失败: {}", e);
+            }
+        },
+        Ok(_) => {},
+        Err(e) => warn!("读取本地 API 设置失败: {}", e),
+    }
+}
+
+// 获取本地 API 设置、令牌和运行状态
+#[tauri::command]
+pub fn get_local_api_status(api: State<'_, LocalApi>) -> Result<LocalApiStatus, String> {
+    status(&api)
+}
+
+// 启用或停用本地 API，port 为空时使用已保存的端口
+#[tauri::command]
+pub fn set_local_api(app: AppHandle, enabled: bool, port: Option<u16>) -> Result<LocalApiStatus, String> {
+    let (_, saved_port, token) = load_settings()?;
+    let port = port.unwrap_or(saved_port);
+    if port == 0 {
+        return Err("端口必须大于 0".to_string());
+    }
+
+    let api = app.state::<LocalApi>();
+    if enabled {
+        api.start(&app, port, token)?;
+    } else {
+        api.stop();
+    }
+
+    let db = get_db()?;
+    db.set_setting(API_ENABLED_KEY, &enabled.to_string())
+        .and_then(|_| db.set_setting(API_PORT_KEY, &port.to_string()))
+        .map_err(|e| format!("保存本地 API 设置失败: {}", e))?;
+    info!("本地 API 已{}，端口 {}", if enabled { "启用" } else { "停用" }, port);
+    status(&api)
+}
+
+// 重新生成令牌，旧令牌立即失效
+#[tauri::command]
+pub fn reset_local_api_token(api: State<'_, LocalApi>) -> Result<LocalApiStatus, String> {
+    let token = local_http::generate_token();
+    get_db()?.set_setting(API_TOKEN_KEY, &token).map_err(|e| format!("保存令牌失败: {}", e))?;
+    api.set_token(token);
+    info!("本地 API 令牌已重新生成");
+    status(&api)
+}
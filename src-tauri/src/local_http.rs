@@ -0,0 +1,266 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{info, warn};
+use serde_json::Value;
+
+// 请求体大小上限
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+// 读取请求的超时
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+// 没有新连接时检查停止标志的间隔
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+// 本地 HTTP 服务收到的请求，服务只监听 127.0.0.1，每个连接处理一个请求
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    // 头部名称统一为小写
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    pub fn json(&self) -> Result<Value, String> {
+        serde_json::from_slice(&self.body).map_err(|e| format!("请求体不是有效的 JSON: {}", e))
+    }
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    },
+                    None => decoded.push(b'%'),
+                }
+            },
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (percent_decode(key), percent_decode(value)),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+fn read_request(stream: &TcpStream) -> Result<HttpRequest, String> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|e| format!("读取请求失败: {}", e))?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("无效的请求行".to_string());
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| format!("读取请求头失败: {}", e))?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length: usize = headers.get("content-length").and_then(|length| length.parse().ok()).unwrap_or_default();
+    if length > MAX_BODY_SIZE {
+        return Err(format!("请求体过大: {} 字节", length));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|e| format!("读取请求体失败: {}", e))?;
+
+    Ok(HttpRequest {
+        method: method.to_ascii_uppercase(),
+        path: path.to_string(),
+        query: parse_query(query),
+        headers,
+        body,
+    })
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+// 写入完整的响应并关闭连接
+pub fn write_response(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> std::io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status, status_text(status), content_type, body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+pub fn write_json(stream: &mut TcpStream, status: u16, value: &Value) -> std::io::Result<()> {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    write_response(stream, status, "application/json", &[], &body)
+}
+
+// 写入 text/event-stream 响应头，之后由调用方持续写入事件
+pub fn write_event_stream_head(stream: &mut TcpStream) -> std::io::Result<()> {
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+    )?;
+    stream.flush()
+}
+
+// 浏览器发起的请求必须来自本机页面，防止 DNS 重绑定攻击
+pub fn is_local_origin(request: &HttpRequest) -> bool {
+    let Some(origin) = request.header("origin") else {
+        return true;
+    };
+    let host = origin.split("://").nth(1).unwrap_or(origin);
+    let host = host.rsplit_once(':').map(|(host, _)| host).unwrap_or(host);
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]" | "tauri.localhost") || origin.starts_with("tauri://")
+}
+
+// 生成随机令牌，RandomState 的密钥来自系统随机数
+pub fn generate_token() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    (0..2u64)
+        .map(|i| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u64(i);
+            hasher.write_u128(nanos);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+// 逐字节比较，比较耗时与令牌内容无关
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// 令牌通过 Authorization: Bearer 或 X-Api-Token 头传递
+pub fn is_authorized(request: &HttpRequest, token: &str) -> bool {
+    let given = request.header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| request.header("x-api-token"));
+    !token.is_empty() && given.is_some_and(|given| token_matches(given.trim(), token))
+}
+
+pub type HttpHandler = Arc<dyn Fn(HttpRequest, TcpStream) + Send + Sync>;
+
+// 运行中的本地 HTTP 服务，drop 时停止监听
+pub struct LocalServer {
+    pub addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+impl LocalServer {
+    // 在 127.0.0.1 上监听，每个连接在独立线程中交给 handler 处理
+    pub fn bind(name: &str, port: u16, handler: HttpHandler) -> Result<LocalServer, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .map_err(|e| format!("{} 监听端口 {} 失败: {}", name, port, e))?;
+        listener.set_nonblocking(true).map_err(|e| format!("设置监听模式失败: {}", e))?;
+        let addr = listener.local_addr().map_err(|e| format!("获取监听地址失败: {}", e))?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_stop = stop.clone();
+        let name = name.to_string();
+        std::thread::spawn(move || {
+            info!("{} 已在 http://{} 上监听", name, addr);
+            while !thread_stop.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let handler = handler.clone();
+                        std::thread::spawn(move || handle_connection(stream, handler));
+                    },
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_INTERVAL),
+                    Err(e) => {
+                        warn!("{} 接受连接失败: {}", name, e);
+                        std::thread::sleep(ACCEPT_INTERVAL);
+                    },
+                }
+            }
+            info!("{} 已停止", name);
+        });
+
+        Ok(LocalServer { addr, stop })
+    }
+}
+
+impl Drop for LocalServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, handler: HttpHandler) {
+    if stream.set_nonblocking(false).is_err() || stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+        return;
+    }
+    match read_request(&stream) {
+        Ok(request) => {
+            if !is_local_origin(&request) {
+                let _ = write_response(&mut stream, 403, "text/plain; charset=utf-8", &[], "不允许的来源".as_bytes());
+                return;
+            }
+            handler(request, stream);
+        },
+        Err(e) => {
+            let _ = write_response(&mut stream, 400, "text/plain; charset=utf-8", &[], e.as_bytes());
+        },
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::mcp_installer;
+use crate::package_installer::{self, PackageEcosystem};
+use crate::save_mcp_config::{self, McpServerConfig};
+use crate::tools;
+
+// 内置 MCP 服务器目录
+const BUILTIN_CATALOG: &str = include_str!("../resources/mcp_catalog.toml");
+// 默认的目录更新地址
+const CATALOG_URL: &str = "https://raw.githubusercontent.com/omni-ai-nodes/omni-mcp-app/main/src-tauri/resources/mcp_catalog.toml";
+
+// 目录文件
+#[derive(Deserialize, Debug, Default)]
+struct CatalogFile {
+    #[serde(default)]
+    servers: Vec<CatalogEntry>,
+}
+
+// 服务器的安装来源
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CatalogSource {
+    Npm {
+        package: String,
+        #[serde(default)]
+        version: Option<String>,
+    },
+    Pypi {
+        package: String,
+        #[serde(default)]
+        version: Option<String>,
+    },
+    Git {
+        url: String,
+        #[serde(default, rename = "ref")]
+        git_ref: Option<String>,
+    },
+}
+
+// 服务器需要的环境变量
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CatalogEnvVar {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    // 是否为密钥，界面中以密码框输入
+    #[serde(default)]
+    pub secret: bool,
+}
+
+// 目录中的服务器
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub source: CatalogSource,
+    #[serde(default)]
+    pub env: Vec<CatalogEnvVar>,
+    // 安装和运行需要的工具，对应工具注册表中的 id
+    #[serde(default)]
+    pub runtime: Option<String>,
+    // 追加到启动命令后的参数
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // 等价的 mcpServers 配置，仅用于展示
+    #[serde(default)]
+    pub example: Option<Value>,
+}
+
+// 搜索结果
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogSearchResult {
+    #[serde(flatten)]
+    pub entry: CatalogEntry,
+    // 是否已安装同名服务器
+    pub installed: bool,
+    // 需要的运行时是否已安装
+    pub runtime_installed: bool,
+}
+
+fn omni_dir() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or_else(|| "无法获取用户主目录".to_string())?;
+    Ok(home.join(".omni-mcp"))
+}
+
+// 下载的目录 (~/.omni-mcp/cache/mcp_catalog.toml)
+fn downloaded_catalog_path() -> Result<PathBuf, String> {
+    Ok(omni_dir()?.join("cache").join("mcp_catalog.toml"))
+}
+
+// 用户自定义的目录 (~/.omni-mcp/mcp_catalog.toml)
+pub fn user_catalog_path() -> Result<PathBuf, String> {
+    Ok(omni_dir()?.join("mcp_catalog.toml"))
+}
+
+fn parse_catalog(content: &str, source: &str) -> Result<Vec<CatalogEntry>, String> {
+    toml::from_str::<CatalogFile>(content)
+        .map(|file| file.servers)
+        .map_err(|e| format!("解析{}失败: {}", source, e))
+}
+
+// 加载目录，优先使用下载的目录，用户条目按 id 覆盖
+pub fn load_catalog() -> Result<Vec<CatalogEntry>, String> {
+    let downloaded = downloaded_catalog_path()?;
+    let mut entries = match std::fs::read_to_string(&downloaded) {
+        Ok(content) => parse_catalog(&content, "下载的服务器目录")?,
+        Err(_) => parse_catalog(BUILTIN_CATALOG, "内置服务器目录")?,
+    };
+
+    let user_path = user_catalog_path()?;
+    if user_path.exists() {
+        let content = std::fs::read_to_string(&user_path)
+            .map_err(|e| format!("读取用户服务器目录失败: {}", e))?;
+        for entry in parse_catalog(&content, "用户服务器目录")? {
+            match entries.iter_mut().find(|existing| existing.id == entry.id) {
+                Some(existing) => *existing = entry,
+                None => entries.push(entry),
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+pub fn find_entry(id: &str) -> Result<CatalogEntry, String> {
+    load_catalog()?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| format!("目录中没有服务器: {}", id))
+}
+
+fn runtime_installed(entry: &CatalogEntry) -> bool {
+    entry.runtime.as_deref()
+        .is_none_or(|runtime| tools::find_tool(runtime).is_ok_and(|tool| tool.check_installed()))
+}
+
+// 按名称、描述、包名和标签搜索目录，关键词为空时返回全部
+pub fn search(query: &str) -> Result<Vec<CatalogSearchResult>, String> {
+    let keywords: Vec<String> = query.split_whitespace().map(|word| word.to_lowercase()).collect();
+
+    Ok(load_catalog()?
+        .into_iter()
+        .filter(|entry| {
+            let source = match &entry.source {
+                CatalogSource::Npm { package, .. } | CatalogSource::Pypi { package, .. } => package,
+                CatalogSource::Git { url, .. } => url,
+            };
+            let text = format!("{} {} {} {} {}", entry.id, entry.name, entry.description, source, entry.tags.join(" "))
+                .to_lowercase();
+            keywords.iter().all(|word| text.contains(word))
+        })
+        .map(|entry| CatalogSearchResult {
+            installed: save_mcp_config::count_mcp_server_config(&entry.id).unwrap_or(0) > 0,
+            runtime_installed: runtime_installed(&entry),
+            entry,
+        })
+        .collect())
+}
+
+// 安装目录中的服务器并写入 mcpServers，env 为用户填写的环境变量
+pub fn install_entry(id: &str, name: Option<&str>, env: HashMap<String, String>) -> Result<McpServerConfig, String> {
+    let entry = find_entry(id)?;
+    let name = name.filter(|name| !name.is_empty()).unwrap_or(&entry.id);
+
+    let missing: Vec<&str> = entry.env.iter()
+        .filter(|var| var.required && env.get(&var.name).is_none_or(|value| value.trim().is_empty()))
+        .map(|var| var.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("缺少环境变量: {}", missing.join(", ")));
+    }
+    if !runtime_installed(&entry) {
+        return Err(format!("请先安装 {}", entry.runtime.unwrap_or_default()));
+    }
+
+    // 只保存目录中声明且已填写的环境变量
+    let env: serde_json::Map<String, Value> = entry.env.iter()
+        .filter_map(|var| {
+            let value = env.get(&var.name).filter(|value| !value.trim().is_empty())?;
+            Some((var.name.clone(), Value::String(value.clone())))
+        })
+        .collect();
+    let env = (!env.is_empty()).then_some(Value::Object(env));
+
+    info!("从目录安装 MCP 服务器 {} ({})", name, entry.id);
+    let mut config = match &entry.source {
+        CatalogSource::Npm { package, version } => package_installer::install_from_package(
+            PackageEcosystem::Npm, package, version.as_deref(), Some(name), entry.args.clone(), env,
+        )?,
+        CatalogSource::Pypi { package, version } => package_installer::install_from_package(
+            PackageEcosystem::Pypi, package, version.as_deref(), Some(name), entry.args.clone(), env,
+        )?,
+        CatalogSource::Git { url, git_ref } => {
+            let installed = mcp_installer::install_from_git(url, git_ref.as_deref(), Some(name))?;
+            let config = McpServerConfig {
+                description: Some(entry.description.clone()),
+                args: installed.args.iter().chain(&entry.args).cloned().collect(),
+                env,
+                ..installed
+            };
+            save_mcp_config::save_mcp_server_config(config.clone())?;
+            config
+        },
+    };
+    config.runtime_status = save_mcp_config::check_server_runtime(&config);
+    Ok(config)
+}
+
+// 下载最新的服务器目录，校验格式后保存，返回条目数
+pub fn update_catalog(url: Option<&str>) -> Result<usize, String> {
+    let url = url.unwrap_or(CATALOG_URL);
+    info!("更新服务器目录: {}", url);
+
+    let output = tools::curl_command(url)
+        .arg("-f")
+        .output()
+        .map_err(|e| format!("下载服务器目录失败: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("下载服务器目录失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let content = String::from_utf8(output.stdout).map_err(|e| format!("服务器目录不是有效的 UTF-8: {}", e))?;
+    let count = parse_catalog(&content, "下载的服务器目录")?.len();
+
+    let path = downloaded_catalog_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    std::fs::write(&path, content).map_err(|e| format!("保存服务器目录失败: {}", e))?;
+    info!("服务器目录已更新，共 {} 个服务器", count);
+    Ok(count)
+}
+
+// 搜索 MCP 服务器目录
+#[tauri::command]
+pub async fn search_mcp_catalog(query: Option<String>) -> Result<Vec<CatalogSearchResult>, String> {
+    tauri::async_runtime::spawn_blocking(move || search(query.as_deref().unwrap_or_default()))
+        .await
+        .map_err(|e| format!("搜索任务异常结束: {}", e))?
+}
+
+// 安装目录中的服务器，name 为空时使用目录 id
+#[tauri::command]
+pub async fn install_mcp_catalog_server(
+    id: String,
+    name: Option<String>,
+    env: Option<HashMap<String, String>>,
+) -> Result<McpServerConfig, String> {
+    tauri::async_runtime::spawn_blocking(move || install_entry(&id, name.as_deref(), env.unwrap_or_default()))
+        .await
+        .map_err(|e| format!("安装任务异常结束: {}", e))?
+}
+
+// 更新服务器目录，url 为空时使用默认地址
+#[tauri::command]
+pub async fn update_mcp_catalog(url: Option<String>) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || update_catalog(url.as_deref()))
+        .await
+        .map_err(|e| format!("更新任务异常结束: {}", e))?
+}
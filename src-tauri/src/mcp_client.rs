@@ -0,0 +1,509 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::docker_runtime;
+use crate::save_mcp_config::McpServerConfig;
+use crate::tools;
+
+// 客户端支持的 MCP 协议版本
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+// 默认请求超时
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// 保留的 stderr 行数，用于错误信息
+const STDERR_TAIL_LINES: usize = 20;
+
+// JSON-RPC 错误
+#[derive(Serialize, Debug, Clone)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl JsonRpcError {
+    pub fn method_not_found(method: &str) -> Self {
+        JsonRpcError { code: -32601, message: format!("不支持的方法: {}", method) }
+    }
+}
+
+// 处理服务器主动发来的请求和通知
+//
+// 通知回调在客户端的读取线程中执行，耗时的处理需要自行放到其他线程；
+// 请求回调在独立线程中执行，可以阻塞等待用户确认
+pub trait ClientHandler: Send + Sync {
+    // initialize 中声明的客户端能力
+    fn capabilities(&self) -> Value {
+        json!({})
+    }
+
+    fn on_notification(&self, _server: &str, _method: &str, _params: Option<Value>) {}
+
+    fn on_request(&self, _server: &str, method: &str, _params: Option<Value>) -> Result<Value, JsonRpcError> {
+        // ping 是双向的，服务器也可以探测客户端
+        if method == "ping" {
+            return Ok(json!({}));
+        }
+        Err(JsonRpcError::method_not_found(method))
+    }
+}
+
+// 不处理任何请求和通知
+pub struct NoopHandler;
+
+impl ClientHandler for NoopHandler {}
+
+type PendingMap = HashMap<u64, mpsc::Sender<Result<Value, String>>>;
+
+// MCP stdio 客户端，每个服务器进程对应一个客户端
+pub struct McpClient {
+    name: String,
+    child: Mutex<Child>,
+    stdin: Arc<Mutex<ChildStdin>>,
+    pending: Arc<Mutex<PendingMap>>,
+    next_id: AtomicU64,
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    // initialize 的返回结果
+    server_info: Value,
+}
+
+// 写入一行 JSON-RPC 消息
+fn write_message(stdin: &Mutex<ChildStdin>, message: &Value) -> Result<(), String> {
+    let mut stdin = stdin.lock().map_err(|e| format!("获取 stdin 失败: {}", e))?;
+    let line = serde_json::to_string(message).map_err(|e| format!("序列化消息失败: {}", e))?;
+    stdin.write_all(line.as_bytes())
+        .and_then(|_| stdin.write_all(b"\n"))
+        .and_then(|_| stdin.flush())
+        .map_err(|e| format!("发送消息失败: {}", e))
+}
+
+fn stderr_summary(stderr_tail: &Mutex<VecDeque<String>>) -> String {
+    stderr_tail.lock()
+        .map(|lines| lines.iter().cloned().collect::<Vec<_>>().join("\n"))
+        .unwrap_or_default()
+}
+
+// 根据服务器配置构造启动命令
+pub fn server_command(config: &McpServerConfig) -> Result<Command, String> {
+    let mut command = match &config.docker {
+        Some(docker) => docker_runtime::docker_command(config, docker)?,
+        None => {
+            if config.command.is_empty() {
+                return Err(format!("服务器 {} 没有启动命令", config.name));
+            }
+            let mut command = Command::new(tools::resolve_command(&config.command));
+            command.args(&config.args);
+            command
+        },
+    };
+
+    if let Some(env) = config.env.as_ref().and_then(|env| env.as_object()) {
+        for (key, value) in env {
+            match value {
+                Value::String(value) => command.env(key, value),
+                other => command.env(key, other.to_string()),
+            };
+        }
+    }
+    if let Some(dir) = config.install_dir.as_deref().filter(|dir| Path::new(dir).is_dir()) {
+        command.current_dir(dir);
+    }
+
+    command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    Ok(command)
+}
+
+impl McpClient {
+    // 启动服务器进程并完成 initialize 握手
+    pub fn connect(config: &McpServerConfig, handler: Arc<dyn ClientHandler>) -> Result<McpClient, String> {
+        let mut command = server_command(config)?;
+        println!("启动 MCP 服务器 {}: {:?}", config.name, command);
+
+        let mut child = command.spawn()
+            .map_err(|e| format!("启动服务器 {} 失败: {}", config.name, e))?;
+        let stdin = Arc::new(Mutex::new(child.stdin.take().ok_or_else(|| "无法获取服务器 stdin".to_string())?));
+        let stdout = child.stdout.take().ok_or_else(|| "无法获取服务器 stdout".to_string())?;
+        let stderr = child.stderr.take();
+
+        let handler_capabilities = handler.capabilities();
+        let pending: Arc<Mutex<PendingMap>> = Arc::new(Mutex::new(HashMap::new()));
+        let stderr_tail = Arc::new(Mutex::new(VecDeque::new()));
+
+        if let Some(stderr) = stderr {
+            let stderr_tail = stderr_tail.clone();
+            let name = config.name.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    println!("[{}] {}", name, line);
+                    if let Ok(mut tail) = stderr_tail.lock() {
+                        if tail.len() >= STDERR_TAIL_LINES {
+                            tail.pop_front();
+                        }
+                        tail.push_back(line);
+                    }
+                }
+            });
+        }
+
+        {
+            let name = config.name.clone();
+            let pending = pending.clone();
+            let stdin = stdin.clone();
+            let stderr_tail = stderr_tail.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let message: Value = match serde_json::from_str(&line) {
+                        Ok(message) => message,
+                        Err(_) => {
+                            // 有些服务器会把日志打印到 stdout
+                            println!("[{}] {}", name, line);
+                            continue;
+                        },
+                    };
+                    Self::dispatch(&name, message, &pending, &stdin, &handler);
+                }
+
+                // 服务器退出，通知所有等待中的请求
+                let reason = format!("服务器 {} 已退出: {}", name, stderr_summary(&stderr_tail));
+                if let Ok(mut pending) = pending.lock() {
+                    for (_, sender) in pending.drain() {
+                        let _ = sender.send(Err(reason.clone()));
+                    }
+                }
+                // 用内部通知告知处理器连接已断开
+                handler.on_notification(&name, "$/closed", None);
+            });
+        }
+
+        let mut client = McpClient {
+            name: config.name.clone(),
+            child: Mutex::new(child),
+            stdin,
+            pending,
+            next_id: AtomicU64::new(1),
+            stderr_tail,
+            server_info: Value::Null,
+        };
+
+        match client.initialize(handler_capabilities) {
+            Ok(server_info) => {
+                client.server_info = server_info;
+                Ok(client)
+            },
+            Err(e) => {
+                client.close();
+                Err(e)
+            },
+        }
+    }
+
+    // 分发服务器发来的消息：响应、请求或通知
+    fn dispatch(
+        name: &str,
+        message: Value,
+        pending: &Mutex<PendingMap>,
+        stdin: &Arc<Mutex<ChildStdin>>,
+        handler: &Arc<dyn ClientHandler>,
+    ) {
+        let method = message.get("method").and_then(|method| method.as_str()).map(String::from);
+        let id = message.get("id").cloned();
+        let params = message.get("params").cloned();
+
+        match (method, id) {
+            (Some(method), Some(id)) => {
+                // 请求可能需要等待用户处理，不能阻塞读取线程
+                let name = name.to_string();
+                let stdin = stdin.clone();
+                let handler = handler.clone();
+                std::thread::spawn(move || {
+                    let response = match handler.on_request(&name, &method, params) {
+                        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+                    };
+                    if let Err(e) = write_message(&stdin, &response) {
+                        warn!("[{}] 回复 {} 失败: {}", name, method, e);
+                    }
+                });
+            },
+            (Some(method), None) => handler.on_notification(name, &method, params),
+            (None, Some(id)) => {
+                let Some(id) = id.as_u64() else {
+                    return;
+                };
+                let result = match message.get("error") {
+                    Some(error) => Err(error.get("message")
+                        .and_then(|message| message.as_str())
+                        .map(String::from)
+                        .unwrap_or_else(|| error.to_string())),
+                    None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                let sender = pending.lock().ok().and_then(|mut pending| pending.remove(&id));
+                if let Some(sender) = sender {
+                    let _ = sender.send(result);
+                }
+            },
+            (None, None) => warn!("[{}] 无法识别的消息: {}", name, message),
+        }
+    }
+
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    pub fn pid(&self) -> Option<u32> {
+        self.child.lock().ok().map(|child| child.id())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.child.lock().is_ok_and(|mut child| matches!(child.try_wait(), Ok(None)))
+    }
+
+    // 是否有尚未收到响应的请求，如仍在执行的工具调用
+    pub fn has_pending_requests(&self) -> bool {
+        self.pending.lock().is_ok_and(|pending| !pending.is_empty())
+    }
+
+    // 调用工具，以 progress_token 接收 notifications/progress
+    //
+    // cancel 被设置或超过 timeout 时向服务器发送 notifications/cancelled 并立即返回，
+    // 不再等待服务器响应
+    pub fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress_token: &str,
+        cancel: &AtomicBool,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = mpsc::channel();
+        self.pending.lock()
+            .map_err(|e| format!("获取请求队列失败: {}", e))?
+            .insert(id, sender);
+
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments, "_meta": { "progressToken": progress_token } },
+        });
+        if let Err(e) = write_message(&self.stdin, &message) {
+            self.forget(id);
+            return Err(e);
+        }
+
+        let started = Instant::now();
+        loop {
+            match receiver.recv_timeout(Duration::from_millis(200)) {
+                Ok(result) => return result.map_err(|e| format!("[{}] 调用工具 {} 失败: {}", self.name, name, e)),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Err(format!("[{}] 连接已断开", self.name)),
+                Err(mpsc::RecvTimeoutError::Timeout) => {},
+            }
+
+            let reason = if cancel.load(Ordering::SeqCst) {
+                "用户取消"
+            } else if started.elapsed() >= timeout {
+                "调用超时"
+            } else {
+                continue;
+            };
+            self.forget(id);
+            if let Err(e) = self.notify("notifications/cancelled", json!({ "requestId": id, "reason": reason })) {
+                warn!("[{}] 发送取消通知失败: {}", self.name, e);
+            }
+            return Err(format!("[{}] 调用工具 {} {}", self.name, name, reason));
+        }
+    }
+
+    // 发送请求并等待响应
+    pub fn request(&self, method: &str, params: Value, timeout: Duration) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = mpsc::channel();
+        self.pending.lock()
+            .map_err(|e| format!("获取请求队列失败: {}", e))?
+            .insert(id, sender);
+
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if let Err(e) = write_message(&self.stdin, &message) {
+            self.forget(id);
+            return Err(e);
+        }
+
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result.map_err(|e| format!("[{}] {} 失败: {}", self.name, method, e)),
+            Err(_) => {
+                self.forget(id);
+                Err(format!("[{}] {} 超时 ({} 秒)", self.name, method, timeout.as_secs()))
+            },
+        }
+    }
+
+    fn forget(&self, id: u64) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&id);
+        }
+    }
+
+    // 发送通知
+    pub fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        write_message(&self.stdin, &json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn initialize(&self, capabilities: Value) -> Result<Value, String> {
+        let result = self.request("initialize", json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": capabilities,
+            "clientInfo": { "name": "omni-mcp-app", "version": env!("CARGO_PKG_VERSION") },
+        }), REQUEST_TIMEOUT).map_err(|e| {
+            let stderr = stderr_summary(&self.stderr_tail);
+            if stderr.is_empty() { e } else { format!("{}\n{}", e, stderr) }
+        })?;
+        self.notify("notifications/initialized", json!({}))?;
+        info!("[{}] 初始化完成: {}", self.name, result.get("serverInfo").unwrap_or(&Value::Null));
+        Ok(result)
+    }
+
+    // 服务器在 initialize 中声明的能力，如 capabilities.resources.subscribe
+    pub fn has_capability(&self, path: &[&str]) -> bool {
+        let mut value = self.server_info.get("capabilities");
+        for key in path {
+            value = value.and_then(|value| value.get(key));
+        }
+        value.is_some_and(|value| !value.is_null() && value != &Value::Bool(false))
+    }
+
+    // 请求列表类方法的全部结果，自动处理分页
+    fn list_all(&self, method: &str, key: &str) -> Result<Vec<Value>, String> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request(method, params, REQUEST_TIMEOUT)?;
+            if let Some(page) = result.get(key).and_then(|items| items.as_array()) {
+                items.extend(page.iter().cloned());
+            }
+            cursor = result.get("nextCursor").and_then(|cursor| cursor.as_str()).map(String::from);
+            if cursor.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    // 获取服务器提供的全部工具
+    pub fn list_tools(&self) -> Result<Vec<Value>, String> {
+        self.list_all("tools/list", "tools")
+    }
+
+    // 获取服务器提供的全部资源，未声明 resources 能力的服务器返回空列表
+    pub fn list_resources(&self) -> Result<Vec<Value>, String> {
+        if !self.has_capability(&["resources"]) {
+            return Ok(Vec::new());
+        }
+        self.list_all("resources/list", "resources")
+    }
+
+    // 获取资源模板，如 file:///{path}
+    pub fn list_resource_templates(&self) -> Result<Vec<Value>, String> {
+        if !self.has_capability(&["resources"]) {
+            return Ok(Vec::new());
+        }
+        self.list_all("resources/templates/list", "resourceTemplates")
+    }
+
+    // 读取资源，返回 contents 数组，每项包含 text 或 base64 编码的 blob
+    pub fn read_resource(&self, uri: &str) -> Result<Vec<Value>, String> {
+        let result = self.request("resources/read", json!({ "uri": uri }), REQUEST_TIMEOUT)?;
+        Ok(result.get("contents")
+            .and_then(|contents| contents.as_array())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    // 获取服务器提供的全部提示词模板，未声明 prompts 能力的服务器返回空列表
+    pub fn list_prompts(&self) -> Result<Vec<Value>, String> {
+        if !self.has_capability(&["prompts"]) {
+            return Ok(Vec::new());
+        }
+        self.list_all("prompts/list", "prompts")
+    }
+
+    // 用参数渲染提示词模板，返回 description 和 messages
+    pub fn get_prompt(&self, name: &str, arguments: &HashMap<String, String>) -> Result<Value, String> {
+        self.request("prompts/get", json!({ "name": name, "arguments": arguments }), REQUEST_TIMEOUT)
+    }
+
+    // 订阅资源变更，服务器通过 notifications/resources/updated 通知
+    pub fn subscribe_resource(&self, uri: &str) -> Result<(), String> {
+        if !self.has_capability(&["resources", "subscribe"]) {
+            return Err(format!("服务器 {} 不支持订阅资源", self.name));
+        }
+        self.request("resources/subscribe", json!({ "uri": uri }), REQUEST_TIMEOUT).map(|_| ())
+    }
+
+    pub fn unsubscribe_resource(&self, uri: &str) -> Result<(), String> {
+        self.request("resources/unsubscribe", json!({ "uri": uri }), REQUEST_TIMEOUT).map(|_| ())
+    }
+
+    // 结束服务器进程
+    pub fn close(&self) {
+        if let Ok(mut child) = self.child.lock() {
+            if matches!(child.try_wait(), Ok(None)) {
+                info!("关闭 MCP 服务器 {}", self.name);
+                tools::kill_process_tree(&mut child);
+            }
+        }
+    }
+}
+
+impl Drop for McpClient {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// 健康检查结果
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheckReport {
+    pub server: String,
+    pub server_version: Option<String>,
+    pub tool_count: usize,
+    pub latency_ms: u128,
+}
+
+// 启动服务器并完成握手和 tools/list，用于安装和升级后的检查
+pub fn health_check(config: &McpServerConfig) -> Result<HealthCheckReport, String> {
+    let started = Instant::now();
+    let client = McpClient::connect(config, Arc::new(NoopHandler))?;
+    let tool_count = client.list_tools()?.len();
+    let report = HealthCheckReport {
+        server: config.name.clone(),
+        server_version: client.server_info()
+            .get("serverInfo")
+            .and_then(|info| info.get("version"))
+            .and_then(|version| version.as_str())
+            .map(String::from),
+        tool_count,
+        latency_ms: started.elapsed().as_millis(),
+    };
+    client.close();
+    Ok(report)
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::mcp_client::JsonRpcError;
+use crate::pending_requests::PendingRequests;
+
+// 前端监听的信息收集请求事件，用户填写后通过 respond_elicitation_request 回复
+pub const ELICITATION_REQUEST_EVENT: &str = "mcp-elicitation-request";
+// 等待用户填写的时间，超时按取消处理
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(600);
+
+// 发送给前端渲染表单的请求
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitationRequest {
+    pub id: String,
+    pub server: String,
+    pub message: String,
+    // 只包含基本类型属性的 JSON Schema
+    pub requested_schema: Value,
+}
+
+// 用户的处理方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitationAction {
+    Accept,
+    Decline,
+    Cancel,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ElicitationResponse {
+    pub action: ElicitationAction,
+    #[serde(default)]
+    pub content: Option<Value>,
+}
+
+// 等待用户填写的信息收集请求，保存各请求的 schema 用于校验回复
+#[derive(Default)]
+pub struct ElicitationRequests {
+    pending: PendingRequests<ElicitationResponse>,
+    schemas: Mutex<HashMap<String, Value>>,
+}
+
+impl ElicitationRequests {
+    fn register(&self, schema: &Value) -> Result<(String, mpsc::Receiver<ElicitationResponse>), String> {
+        let (id, receiver) = self.pending.register("elicitation")?;
+        if let Ok(mut schemas) = self.schemas.lock() {
+            schemas.insert(id.clone(), schema.clone());
+        }
+        Ok((id, receiver))
+    }
+
+    fn wait(&self, id: &str, receiver: mpsc::Receiver<ElicitationResponse>) -> Option<ElicitationResponse> {
+        let response = self.pending.wait(id, receiver, RESPONSE_TIMEOUT);
+        self.forget(id);
+        response
+    }
+
+    fn forget(&self, id: &str) {
+        self.pending.remove(id);
+        if let Ok(mut schemas) = self.schemas.lock() {
+            schemas.remove(id);
+        }
+    }
+
+    // 接受时按 schema 校验内容，校验失败时请求保持等待，用户可以修改后重新提交
+    pub fn respond(&self, id: &str, response: ElicitationResponse) -> Result<(), String> {
+        if response.action == ElicitationAction::Accept {
+            let schema = self.schemas.lock()
+                .map_err(|e| format!("获取信息收集请求失败: {}", e))?
+                .get(id)
+                .cloned()
+                .ok_or_else(|| format!("请求不存在或已超时: {}", id))?;
+            validate_content(&schema, response.content.as_ref().unwrap_or(&json!({})))?;
+        }
+        self.pending.respond(id, response)
+    }
+}
+
+// 按 requestedSchema 检查用户填写的内容：必填字段、基本类型和枚举值
+fn validate_content(schema: &Value, content: &Value) -> Result<(), String> {
+    let content = content.as_object().ok_or_else(|| "填写的内容必须是对象".to_string())?;
+    let properties = schema.get("properties").and_then(|properties| properties.as_object());
+
+    if let Some(required) = schema.get("required").and_then(|required| required.as_array()) {
+        for field in required.iter().filter_map(|field| field.as_str()) {
+            if content.get(field).is_none_or(|value| value.is_null()) {
+                return Err(format!("缺少必填字段: {}", field));
+            }
+        }
+    }
+
+    for (field, value) in content {
+        let Some(property) = properties.and_then(|properties| properties.get(field)) else {
+            return Err(format!("未知字段: {}", field));
+        };
+        let type_matches = match property.get("type").and_then(|kind| kind.as_str()) {
+            Some("string") => value.is_string(),
+            Some("number") => value.is_number(),
+            Some("integer") => value.is_i64() || value.is_u64(),
+            Some("boolean") => value.is_boolean(),
+            _ => true,
+        };
+        if !type_matches {
+            return Err(format!("字段 {} 的类型不正确", field));
+        }
+        if let Some(options) = property.get("enum").and_then(|options| options.as_array()) {
+            if !options.contains(value) {
+                return Err(format!("字段 {} 的值不在可选范围内", field));
+            }
+        }
+    }
+    Ok(())
+}
+
+// 处理服务器的 elicitation/create 请求：通知前端渲染表单，等待用户接受、拒绝或取消
+//
+// 请求在客户端的独立线程中处理，等待期间不影响其他服务器和同一服务器的其他消息
+pub fn handle_create(app: &AppHandle, server: &str, params: Option<Value>) -> Result<Value, JsonRpcError> {
+    let params = params.unwrap_or(Value::Null);
+    let internal = |message: String| JsonRpcError { code: -32603, message };
+
+    let schema = params.get("requestedSchema").cloned().unwrap_or_else(|| json!({ "type": "object", "properties": {} }));
+    let requests = app.state::<ElicitationRequests>();
+    let (id, receiver) = requests.register(&schema).map_err(internal)?;
+    let request = ElicitationRequest {
+        id: id.clone(),
+        server: server.to_string(),
+        message: params.get("message").and_then(|message| message.as_str()).unwrap_or_default().to_string(),
+        requested_schema: schema,
+    };
+
+    info!("服务器 {} 请求用户输入: {}", server, id);
+    if let Err(e) = app.emit(ELICITATION_REQUEST_EVENT, &request) {
+        requests.forget(&id);
+        return Err(internal(format!("发送信息收集事件失败: {}", e)));
+    }
+
+    let Some(response) = requests.wait(&id, receiver) else {
+        warn!("信息收集请求 {} 等待超时", id);
+        return Ok(json!({ "action": ElicitationAction::Cancel }));
+    };
+    info!("信息收集请求 {}: {:?}", id, response.action);
+    match response.action {
+        ElicitationAction::Accept => Ok(json!({
+            "action": ElicitationAction::Accept,
+            "content": response.content.unwrap_or_else(|| json!({})),
+        })),
+        action => Ok(json!({ "action": action })),
+    }
+}
+
+// 回复服务器的信息收集请求
+#[tauri::command]
+pub fn respond_elicitation_request(
+    requests: State<'_, ElicitationRequests>,
+    id: String,
+    response: ElicitationResponse,
+) -> Result<(), String> {
+    requests.respond(&id, response)
+}
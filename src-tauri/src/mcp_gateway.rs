@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::local_http::{self, HttpRequest, LocalServer};
+use crate::mcp_client::{JsonRpcError, PROTOCOL_VERSION};
+use crate::mcp_server_tools;
+use crate::pending_requests::PendingRequests;
+use crate::sqlite_db::Database;
+
+// 网关默认端口，stdio 辅助程序使用同一个默认值
+pub const DEFAULT_GATEWAY_PORT: u16 = 17321;
+// app_settings 中的网关设置
+const GATEWAY_ENABLED_KEY: &str = "gateway_enabled";
+const GATEWAY_PORT_KEY: &str = "gateway_port";
+const GATEWAY_TOKEN_KEY: &str = "gateway_token";
+// SSE 连接的心跳间隔，用于发现已断开的客户端
+const SSE_KEEPALIVE: Duration = Duration::from_secs(15);
+// 前端监听的工具调用确认事件，用户确认后通过 respond_gateway_tool_call 回复
+pub const TOOL_CALL_REQUEST_EVENT: &str = "mcp-gateway-tool-call-request";
+// 等待用户确认的时间
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(600);
+
+// 网关设置和运行状态
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    // Streamable HTTP 地址
+    pub url: String,
+    // 旧版 HTTP+SSE 地址
+    pub sse_url: String,
+    // 客户端需要在 Authorization: Bearer 头中携带的令牌
+    pub token: String,
+}
+
+// 网关客户端发起的工具调用，发送给前端确认
+#[derive(Serialize, Debug, Clone)]
+pub struct GatewayToolCall {
+    pub id: String,
+    // 带命名空间的工具名称，如 github__search
+    pub name: String,
+    pub arguments: Option<Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ToolCallDecision {
+    pub approved: bool,
+}
+
+// 等待用户确认的网关工具调用
+pub type ToolCallApprovals = PendingRequests<ToolCallDecision>;
+
+// 旧版 SSE 传输的会话，响应通过会话的事件流发回客户端
+#[derive(Default)]
+struct SseSessions {
+    senders: Mutex<HashMap<String, mpsc::Sender<Value>>>,
+    next_id: AtomicU64,
+}
+
+// 把所有启用的服务器聚合为一个本地 MCP 服务器
+//
+// 工具名称按服务器加命名空间，调用经过与应用内相同的工具启用设置，
+// 工具调用以及服务器发起的采样和信息收集请求都需要在应用中确认。
+// 本机的其他进程同样可以连接 127.0.0.1，每个请求都需要携带令牌
+#[derive(Default)]
+pub struct McpGateway {
+    server: Mutex<Option<LocalServer>>,
+    token: Mutex<String>,
+}
+
+fn get_db() -> Result<Database, String> {
+    let db = Database::new().map_err(|e| format!("初始化数据库失败: {}", e))?;
+    db.init_app_settings_table().map_err(|e| format!("初始化表失败: {}", e))?;
+    Ok(db)
+}
+
+// 读取设置，还没有令牌时生成并保存
+fn load_settings() -> Result<(bool, u16, String), String> {
+    let db = get_db()?;
+    let enabled = db.get_setting(GATEWAY_ENABLED_KEY).is_some_and(|value| value == "true");
+    let port = db.get_setting(GATEWAY_PORT_KEY)
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_GATEWAY_PORT);
+    let token = match db.get_setting(GATEWAY_TOKEN_KEY).filter(|token| !token.is_empty()) {
+        Some(token) => token,
+        None => {
+            let token = local_http::generate_token();
+            db.set_setting(GATEWAY_TOKEN_KEY, &token).map_err(|e| format!("保存令牌失败: {}", e))?;
+            token
+        },
+    };
+    Ok((enabled, port, token))
+}
+
+// 网关的端口和令牌，供 stdio 辅助程序连接网关
+pub fn client_settings() -> Result<(u16, String), String> {
+    let (_, port, token) = load_settings()?;
+    Ok((port, token))
+}
+
+fn tools_list() -> Result<Value, JsonRpcError> {
+    let tools = tauri::async_runtime::block_on(mcp_server_tools::agent_tools())
+        .map_err(|message| JsonRpcError { code: -32603, message })?;
+    Ok(json!({
+        "tools": tools.into_iter()
+            .map(|tool| json!({
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": tool.input_schema,
+            }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+fn tools_call(app: &AppHandle, params: Option<&Value>) -> Result<Value, JsonRpcError> {
+    let name = params.and_then(|params| params.get("name")).and_then(|name| name.as_str())
+        .ok_or_else(|| JsonRpcError { code: -32602, message: "缺少工具名称".to_string() })?;
+    let arguments = params.and_then(|params| params.get("arguments")).cloned();
+
+    // 外部客户端的调用与应用内的采样请求一样，需要用户在应用中确认
+    if let Err(e) = request_approval(app, name, arguments.clone()) {
+        return Ok(tool_error(e));
+    }
+
+    info!("网关调用工具 {}", name);
+    let result = tauri::async_runtime::block_on(
+        mcp_server_tools::call_agent_tool(app.clone(), name.to_string(), arguments, None, None)
+    );
+    Ok(result.unwrap_or_else(tool_error))
+}
+
+// 工具执行失败或被拒绝时作为工具结果返回，让调用方的模型看到错误信息
+fn tool_error(message: String) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": message }],
+        "isError": true,
+    })
+}
+
+// 通知前端确认工具调用并等待结果，拒绝或超时返回错误
+fn request_approval(app: &AppHandle, name: &str, arguments: Option<Value>) -> Result<(), String> {
+    let approvals = app.state::<ToolCallApprovals>();
+    let (id, receiver) = approvals.register("gateway-call")?;
+    let request = GatewayToolCall { id: id.clone(), name: name.to_string(), arguments };
+
+    info!("网关请求调用工具 {}: {}", name, id);
+    if let Err(e) = app.emit(TOOL_CALL_REQUEST_EVENT, &request) {
+        approvals.remove(&id);
+        return Err(format!("发送工具调用确认事件失败: {}", e));
+    }
+    match approvals.wait(&id, receiver, APPROVAL_TIMEOUT) {
+        Some(decision) if decision.approved => Ok(()),
+        Some(_) => {
+            info!("用户拒绝了网关工具调用 {}", id);
+            Err("用户拒绝了工具调用".to_string())
+        },
+        None => {
+            warn!("网关工具调用 {} 等待确认超时", id);
+            Err("等待用户确认超时".to_string())
+        },
+    }
+}
+
+// 处理一条 JSON-RPC 消息，通知和客户端的响应没有返回值
+fn handle_message(app: &AppHandle, message: &Value) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let method = message.get("method").and_then(|method| method.as_str())?;
+    let params = message.get("params");
+
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": params
+                .and_then(|params| params.get("protocolVersion"))
+                .and_then(|version| version.as_str())
+                .unwrap_or(PROTOCOL_VERSION),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "omni-mcp-gateway", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => tools_list(),
+        "tools/call" => tools_call(app, params),
+        _ => Err(JsonRpcError::method_not_found(method)),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    })
+}
+
+// 处理单条消息或批量消息
+fn handle_payload(app: &AppHandle, payload: &Value) -> Option<Value> {
+    match payload.as_array() {
+        Some(messages) => {
+            let responses: Vec<Value> = messages.iter().filter_map(|message| handle_message(app, message)).collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        },
+        None => handle_message(app, payload),
+    }
+}
+
+fn parse_error(stream: &mut TcpStream, message: String) {
+    let _ = local_http::write_json(stream, 400, &json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": -32700, "message": message },
+    }));
+}
+
+// Streamable HTTP：POST 发送消息，响应直接作为 JSON 返回
+fn handle_streamable(app: &AppHandle, request: HttpRequest, mut stream: TcpStream) {
+    if request.method != "POST" {
+        let _ = local_http::write_response(&mut stream, 405, "text/plain", &[("Allow", "POST")], b"");
+        return;
+    }
+    let payload = match request.json() {
+        Ok(payload) => payload,
+        Err(e) => return parse_error(&mut stream, e),
+    };
+    let _ = match handle_payload(app, &payload) {
+        Some(response) => local_http::write_json(&mut stream, 200, &response),
+        None => local_http::write_response(&mut stream, 202, "text/plain", &[], b""),
+    };
+}
+
+// 旧版 HTTP+SSE：GET 建立事件流，先发送 endpoint 事件告知消息地址
+fn handle_sse_stream(sessions: &SseSessions, mut stream: TcpStream) {
+    let session_id = format!("session-{}", sessions.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+    let (sender, receiver) = mpsc::channel();
+    if let Ok(mut senders) = sessions.senders.lock() {
+        senders.insert(session_id.clone(), sender);
+    }
+
+    let opened = local_http::write_event_stream_head(&mut stream)
+        .and_then(|_| write!(stream, "event: endpoint\ndata: /messages?sessionId={}\n\n", session_id))
+        .and_then(|_| stream.flush());
+    if opened.is_ok() {
+        info!("网关 SSE 会话 {} 已连接", session_id);
+        loop {
+            let written = match receiver.recv_timeout(SSE_KEEPALIVE) {
+                Ok(message) => write!(stream, "event: message\ndata: {}\n\n", message),
+                Err(mpsc::RecvTimeoutError::Timeout) => write!(stream, ": keepalive\n\n"),
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
+            if written.and_then(|_| stream.flush()).is_err() {
+                break;
+            }
+        }
+    }
+
+    if let Ok(mut senders) = sessions.senders.lock() {
+        senders.remove(&session_id);
+    }
+    info!("网关 SSE 会话 {} 已断开", session_id);
+}
+
+// 旧版 HTTP+SSE：POST 到 endpoint 地址，立即返回 202，响应通过事件流发送
+fn handle_sse_message(app: &AppHandle, sessions: &SseSessions, request: HttpRequest, mut stream: TcpStream) {
+    let sender = request.query.get("sessionId")
+        .and_then(|id| sessions.senders.lock().ok()?.get(id).cloned());
+    let Some(sender) = sender else {
+        let _ = local_http::write_response(&mut stream, 404, "text/plain; charset=utf-8", &[], "会话不存在".as_bytes());
+        return;
+    };
+    let payload = match request.json() {
+        Ok(payload) => payload,
+        Err(e) => return parse_error(&mut stream, e),
+    };
+    let _ = local_http::write_response(&mut stream, 202, "text/plain", &[], b"");
+    drop(stream);
+
+    if let Some(response) = handle_payload(app, &payload) {
+        let _ = sender.send(response);
+    }
+}
+
+impl McpGateway {
+    pub fn start(&self, app: &AppHandle, port: u16, token: String) -> Result<(), String> {
+        self.set_token(token);
+        let mut server = self.server.lock().map_err(|e| format!("获取网关状态失败: {}", e))?;
+        if server.as_ref().is_some_and(|server| server.addr.port() == port) {
+            return Ok(());
+        }
+        // 端口变化时先停止旧的监听
+        server.take();
+
+        let app = app.clone();
+        let sessions = Arc::new(SseSessions::default());
+        let handler: local_http::HttpHandler = Arc::new(move |request, mut stream| {
+            let token = app.state::<McpGateway>().token();
+            if !local_http::is_authorized(&request, &token) {
+                let _ = local_http::write_response(
+                    &mut stream, 401, "text/plain; charset=utf-8", &[("WWW-Authenticate", "Bearer")], "缺少或无效的令牌".as_bytes(),
+                );
+                return;
+            }
+            match (request.method.as_str(), request.path.as_str()) {
+                (_, "/mcp") => handle_streamable(&app, request, stream),
+                ("GET", "/sse") => handle_sse_stream(&sessions, stream),
+                ("POST", "/messages") => handle_sse_message(&app, &sessions, request, stream),
+                _ => {
+                    let _ = local_http::write_response(&mut stream, 404, "text/plain", &[], b"");
+                },
+            }
+        });
+        *server = Some(LocalServer::bind("MCP 网关", port, handler)?);
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Ok(mut server) = self.server.lock() {
+            server.take();
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.server.lock().is_ok_and(|server| server.is_some())
+    }
+
+    fn token(&self) -> String {
+        self.token.lock().map(|token| token.clone()).unwrap_or_default()
+    }
+
+    fn set_token(&self, token: String) {
+        if let Ok(mut current) = self.token.lock() {
+            *current = token;
+        }
+    }
+}
+
+fn status(gateway: &McpGateway) -> Result<GatewayStatus, String> {
+    let (enabled, port, token) = load_settings()?;
+    Ok(GatewayStatus {
+        enabled,
+        running: gateway.is_running(),
+        port,
+        url: format!("http://127.0.0.1:{}/mcp", port),
+        sse_url: format!("http://127.0.0.1:{}/sse", port),
+        token,
+    })
+}
+
+// 应用启动时按设置启动网关
+pub fn start_if_enabled(app: &AppHandle) {
+    match load_settings() {
+        Ok((true, port, token)) => {
+            if let Err(e) = app.state::<McpGateway>().start(app, port, token) {
+                warn!("启动 MCP 网关失败: {}", e);
+            }
+        },
+        Ok(_) => {},
+        Err(e) => warn!("读取网关设置失败: {}", e),
+    }
+}
+
+// 获取网关设置、令牌和运行状态
+#[tauri::command]
+pub fn get_mcp_gateway_status(gateway: State<'_, McpGateway>) -> Result<GatewayStatus, String> {
+    status(&gateway)
+}
+
+// 启用或停用网关，port 为空时使用已保存的端口
+#[tauri::command]
+pub fn set_mcp_gateway(app: AppHandle, enabled: bool, port: Option<u16>) -> Result<GatewayStatus, String> {
+    let (_, saved_port, token) = load_settings()?;
+    let port = port.unwrap_or(saved_port);
+    if port == 0 {
+        return Err("端口必须大于 0".to_string());
+    }
+
+    let gateway = app.state::<McpGateway>();
+    if enabled {
+        gateway.start(&app, port, token)?;
+    } else {
+        gateway.stop();
+    }
+
+    let db = get_db()?;
+    db.set_setting(GATEWAY_ENABLED_KEY, &enabled.to_string())
+        .and_then(|_| db.set_setting(GATEWAY_PORT_KEY, &port.to_string()))
+        .map_err(|e| format!("保存网关设置失败: {}", e))?;
+    info!("MCP 网关已{}，端口 {}", if enabled { "启用" } else { "停用" }, port);
+    status(&gateway)
+}
+
+// 重新生成令牌，旧令牌立即失效，客户端需要更新配置
+#[tauri::command]
+pub fn reset_mcp_gateway_token(gateway: State<'_, McpGateway>) -> Result<GatewayStatus, String> {
+    let token = local_http::generate_token();
+    get_db()?.set_setting(GATEWAY_TOKEN_KEY, &token).map_err(|e| format!("保存令牌失败: {}", e))?;
+    gateway.set_token(token);
+    info!("MCP 网关令牌已重新生成");
+    status(&gateway)
+}
+
+// 回复网关的工具调用确认
+#[tauri::command]
+pub fn respond_gateway_tool_call(
+    approvals: State<'_, ToolCallApprovals>,
+    id: String,
+    decision: ToolCallDecision,
+) -> Result<(), String> {
+    approvals.respond(&id, decision)
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Manager, State};
+
+use crate::mcp_supervisor::{self, McpSupervisor};
+use crate::save_mcp_config::{self, McpServerConfig};
+use crate::tools;
+
+// 健康检查间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+// 单次 ping 或 HTTP 探测的超时
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 服务器最近一次健康检查的结果
+#[derive(Default, Clone, Debug)]
+struct HealthRecord {
+    latency_ms: Option<u64>,
+    last_error: Option<String>,
+    // 最近一次检查时间，Unix 秒
+    last_check: Option<u64>,
+    // 本次运行中启动的次数
+    starts: u32,
+    // 最近一次尝试启动的时间，Unix 秒，包括失败的启动
+    last_start: Option<u64>,
+    // 最近一次成功启动后连续失败的次数
+    failed_starts: u32,
+}
+
+// 记录各服务器的健康检查结果和重启次数
+#[derive(Default)]
+pub struct HealthMonitor {
+    records: Mutex<HashMap<String, HealthRecord>>,
+}
+
+impl HealthMonitor {
+    pub fn record_start(&self, name: &str) {
+        if let Ok(mut records) = self.records.lock() {
+            let record = records.entry(name.to_string()).or_default();
+            record.starts += 1;
+            record.last_start = Some(mcp_supervisor::unix_now());
+            record.failed_starts = 0;
+        }
+    }
+
+    pub fn record_start_failure(&self, name: &str) {
+        if let Ok(mut records) = self.records.lock() {
+            let record = records.entry(name.to_string()).or_default();
+            record.failed_starts += 1;
+            record.last_start = Some(mcp_supervisor::unix_now());
+        }
+    }
+
+    // 重启次数（包括失败的启动）和最近一次尝试启动的时间，用于自动重启的退避
+    pub fn restart_attempts(&self, name: &str) -> (u32, Option<u64>) {
+        let record = self.record(name);
+        (record.starts.saturating_sub(1).saturating_add(record.failed_starts), record.last_start)
+    }
+
+    fn record_check(&self, name: &str, result: Result<Duration, String>) {
+        let Ok(mut records) = self.records.lock() else {
+            return;
+        };
+        let record = records.entry(name.to_string()).or_default();
+        record.last_check = Some(mcp_supervisor::unix_now());
+        match result {
+            Ok(latency) => {
+                record.latency_ms = Some(latency.as_millis() as u64);
+                record.last_error = None;
+            },
+            Err(e) => {
+                record.latency_ms = None;
+                record.last_error = Some(e);
+            },
+        }
+    }
+
+    fn record(&self, name: &str) -> HealthRecord {
+        self.records.lock().ok()
+            .and_then(|records| records.get(name).cloned())
+            .unwrap_or_default()
+    }
+}
+
+// 服务器配置及运行状态，供 MCP 服务页面展示
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerStatus {
+    #[serde(flatten)]
+    pub config: McpServerConfig,
+    pub running: bool,
+    pub healthy: bool,
+    pub pid: Option<u32>,
+    // 运行时长，秒
+    pub uptime_secs: Option<u64>,
+    pub latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub last_check: Option<u64>,
+    pub restart_count: u32,
+}
+
+fn is_sse(config: &McpServerConfig) -> bool {
+    config.type_.as_deref() == Some("sse")
+}
+
+// 对 SSE 服务器发送 HTTP 请求，以收到响应头的时间作为延迟
+//
+// SSE 连接不会主动结束，超时退出时只要状态码正常即视为可用
+fn probe_http(url: &str) -> Result<Duration, String> {
+    // 丢弃响应体，Windows 没有 /dev/null
+    let null_device = if cfg!(target_os = "windows") { "NUL" } else { "/dev/null" };
+    let output = tools::curl_command(url)
+        .args(["-o", null_device, "-w", "%{http_code} %{time_starttransfer}"])
+        .args(["--max-time", &CHECK_TIMEOUT.as_secs().to_string()])
+        .args(["-H", "Accept: text/event-stream"])
+        .output()
+        .map_err(|e| format!("执行 curl 失败: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut parts = stdout.split_whitespace();
+    let status: u16 = parts.next().and_then(|code| code.parse().ok()).unwrap_or_default();
+    let seconds: f64 = parts.next().and_then(|time| time.parse().ok()).unwrap_or_default();
+    match status {
+        0 => Err(format!("无法连接 {}", url)),
+        200..=399 => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("HTTP 状态码 {}", status)),
+    }
+}
+
+// 检查一遍所有运行中的 stdio 服务器和启用的 SSE 服务器
+fn check_all(app: &AppHandle) {
+    let monitor = app.state::<HealthMonitor>();
+    let supervisor = app.state::<McpSupervisor>();
+
+    for status in supervisor.list() {
+        let result = match supervisor.running_client(&status.name) {
+            Some(client) => {
+                let started = Instant::now();
+                client.request("ping", json!({}), CHECK_TIMEOUT).map(|_| started.elapsed())
+            },
+            None => Err("服务器进程已退出".to_string()),
+        };
+        monitor.record_check(&status.name, result);
+    }
+
+    let servers = match tauri::async_runtime::block_on(save_mcp_config::get_all_mcp_servers(Some(true))) {
+        Ok(servers) => servers,
+        Err(e) => {
+            warn!("健康检查读取服务器配置失败: {}", e);
+            return;
+        },
+    };
+    for server in servers.iter().filter(|server| is_sse(server)) {
+        let result = match &server.base_url {
+            Some(url) => probe_http(url),
+            None => Err("没有配置 baseUrl".to_string()),
+        };
+        monitor.record_check(&server.name, result);
+    }
+}
+
+// 启动后台健康检查线程
+pub fn spawn_monitor(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+        check_all(&app);
+    });
+}
+
+// 获取所有服务器的配置和运行状态
+#[tauri::command]
+pub async fn get_mcp_servers_status(
+    monitor: State<'_, HealthMonitor>,
+    supervisor: State<'_, McpSupervisor>,
+) -> Result<Vec<McpServerStatus>, String> {
+    let servers = save_mcp_config::get_all_mcp_servers(None).await?;
+    let now = mcp_supervisor::unix_now();
+
+    Ok(servers.into_iter()
+        .map(|config| {
+            let record = monitor.record(&config.name);
+            let process = supervisor.status(&config.name);
+            // SSE 服务器由远端运行，以最近一次探测结果判断是否可用
+            let running = match &process {
+                Some(process) => process.running,
+                None => is_sse(&config) && config.is_active && record.last_check.is_some() && record.last_error.is_none(),
+            };
+            McpServerStatus {
+                running,
+                healthy: running && record.last_error.is_none(),
+                pid: process.as_ref().and_then(|process| process.pid),
+                uptime_secs: process.as_ref()
+                    .filter(|process| process.running)
+                    .map(|process| now.saturating_sub(process.started_at)),
+                latency_ms: record.latency_ms,
+                last_error: record.last_error,
+                last_check: record.last_check,
+                restart_count: record.starts.saturating_sub(1),
+                config,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,754 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::{error, info, warn};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::docker_runtime::DockerConfig;
+use crate::mcp_client::{self, HealthCheckReport};
+use crate::mcp_supervisor::McpSupervisor;
+use crate::save_mcp_config::{self, get_mcp_servers_root_dir, McpServerConfig};
+use crate::tools;
+
+// 根据项目文件识别出的项目类型，按检测顺序排列
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ProjectType {
+    Node,
+    Python,
+    Rust,
+    Docker,
+}
+
+impl ProjectType {
+    // 根据目录中的项目文件识别项目类型
+    pub fn detect(dir: &Path) -> Option<ProjectType> {
+        [
+            ("package.json", ProjectType::Node),
+            ("pyproject.toml", ProjectType::Python),
+            ("Cargo.toml", ProjectType::Rust),
+            ("Dockerfile", ProjectType::Docker),
+        ]
+        .into_iter()
+        .find(|(file, _)| dir.join(file).is_file())
+        .map(|(_, project_type)| project_type)
+    }
+}
+
+// 服务器名称只允许字母、数字、点、下划线和连字符，避免跳出服务器目录
+pub fn validate_server_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("服务器名称无效: {}", name))
+    }
+}
+
+// 从仓库地址推断服务器名称，如 https://github.com/org/mcp-server.git -> mcp-server
+pub fn server_name_from_url(url: &str) -> Result<String, String> {
+    let name = url.trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git")
+        .to_string();
+    validate_server_name(&name)?;
+    Ok(name)
+}
+
+// 查找注册表中工具的可执行文件，未安装时提示用户先安装
+pub fn tool_executable(id: &str) -> Result<PathBuf, String> {
+    let tool = tools::find_tool(id)?;
+    tool.find_executable()
+        .ok_or_else(|| format!("未安装 {}，{}", tool.name, tool.install_hint()))
+}
+
+// 在指定目录中执行命令，失败时返回标准错误输出
+pub fn run_in_dir(program: &Path, args: &[&str], dir: &Path) -> Result<String, String> {
+    run_with_env(program, args, dir, &[])
+}
+
+// 同 run_in_dir，额外设置环境变量
+pub fn run_with_env(program: &Path, args: &[&str], dir: &Path, envs: &[(&str, &Path)]) -> Result<String, String> {
+    let program_name = program.file_name().unwrap_or_default().to_string_lossy().to_string();
+    println!("执行: {} {:?} (目录: {:?})", program_name, args, dir);
+
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .envs(envs.iter().copied())
+        .output()
+        .map_err(|e| format!("执行 {} 失败: {}", program_name, e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let message = if stderr.trim().is_empty() { stdout } else { stderr };
+        return Err(format!("{} {} 失败: {}", program_name, args.join(" "), message.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// 服务器的启动命令
+pub struct LaunchCommand {
+    pub command: String,
+    pub args: Vec<String>,
+    // 以 docker 类型运行时的容器设置
+    pub docker: Option<DockerConfig>,
+}
+
+pub fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+// 读取 JSON 文件
+pub fn read_json(path: &Path) -> Result<Value, String> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    std::fs::read_to_string(path)
+        .map_err(|e| format!("读取 {} 失败: {}", file_name, e))
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| format!("解析 {} 失败: {}", file_name, e)))
+}
+
+// 从 package.json 中找到入口文件：优先 bin（有多个时优先与 preferred 同名的），其次 main
+pub fn node_entry(dir: &Path, package: &Value, preferred: Option<&str>) -> Option<PathBuf> {
+    let bin = match package.get("bin") {
+        Some(Value::String(bin)) => Some(bin.clone()),
+        Some(Value::Object(bins)) => preferred
+            .and_then(|name| bins.get(name))
+            .or_else(|| bins.values().next())
+            .and_then(|bin| bin.as_str())
+            .map(String::from),
+        _ => None,
+    };
+    bin.or_else(|| package.get("main").and_then(|main| main.as_str()).map(String::from))
+        .map(|entry| dir.join(entry))
+}
+
+// 安装 Node 项目依赖，入口文件不存在时执行 build 脚本
+fn install_node(dir: &Path) -> Result<LaunchCommand, String> {
+    let bun = tool_executable("bun")?;
+    run_in_dir(&bun, &["install"], dir)?;
+
+    let package = read_json(&dir.join("package.json"))?;
+
+    let entry = node_entry(dir, &package, None);
+    let has_build_script = package.get("scripts").and_then(|scripts| scripts.get("build")).is_some();
+    if has_build_script && !entry.as_ref().is_some_and(|entry| entry.exists()) {
+        run_in_dir(&bun, &["run", "build"], dir)?;
+    }
+
+    let entry = entry
+        .or_else(|| ["dist/index.js", "build/index.js", "index.js"].iter().map(|file| dir.join(file)).find(|file| file.exists()))
+        .ok_or_else(|| "无法从 package.json 中确定入口文件".to_string())?;
+    if !entry.exists() {
+        return Err(format!("入口文件不存在: {}", entry.to_string_lossy()));
+    }
+
+    Ok(LaunchCommand {
+        command: node_runtime().to_string(),
+        args: vec![path_arg(&entry)],
+        docker: None,
+    })
+}
+
+// 运行 JavaScript 入口文件的命令，优先使用 node，未安装 node 时使用 bun
+pub fn node_runtime() -> &'static str {
+    tools::find_tool("node")
+        .ok()
+        .filter(|node| node.check_installed())
+        .map(|_| "node")
+        .unwrap_or("bun")
+}
+
+// 使用 uv 创建虚拟环境并安装依赖，通过 uv run 启动 [project.scripts] 中的第一个脚本
+fn install_python(dir: &Path) -> Result<LaunchCommand, String> {
+    let uv = tool_executable("uv")?;
+    run_in_dir(&uv, &["sync"], dir)?;
+
+    let pyproject: toml::Value = std::fs::read_to_string(dir.join("pyproject.toml"))
+        .map_err(|e| format!("读取 pyproject.toml 失败: {}", e))
+        .and_then(|content| toml::from_str(&content).map_err(|e| format!("解析 pyproject.toml 失败: {}", e)))?;
+    let project = pyproject.get("project");
+
+    let script = project
+        .and_then(|project| project.get("scripts"))
+        .and_then(|scripts| scripts.as_table())
+        .and_then(|scripts| scripts.keys().next().cloned());
+
+    let mut args = vec!["run".to_string(), "--directory".to_string(), path_arg(dir)];
+    match script {
+        Some(script) => args.push(script),
+        None => {
+            // 没有脚本时按模块方式运行
+            let module = project
+                .and_then(|project| project.get("name"))
+                .and_then(|name| name.as_str())
+                .ok_or_else(|| "pyproject.toml 中缺少 project.scripts 和 project.name".to_string())?
+                .replace('-', "_");
+            args.extend(["python".to_string(), "-m".to_string(), module]);
+        },
+    }
+
+    Ok(LaunchCommand {
+        command: "uv".to_string(),
+        args,
+        docker: None,
+    })
+}
+
+// 使用 cargo 编译 release 版本，启动编译出的可执行文件
+fn install_rust(dir: &Path) -> Result<LaunchCommand, String> {
+    let cargo = which_cargo()?;
+    run_in_dir(&cargo, &["build", "--release"], dir)?;
+
+    let manifest: toml::Value = std::fs::read_to_string(dir.join("Cargo.toml"))
+        .map_err(|e| format!("读取 Cargo.toml 失败: {}", e))
+        .and_then(|content| toml::from_str(&content).map_err(|e| format!("解析 Cargo.toml 失败: {}", e)))?;
+
+    let bin_name = manifest.get("bin")
+        .and_then(|bins| bins.as_array())
+        .and_then(|bins| bins.first())
+        .and_then(|bin| bin.get("name"))
+        .or_else(|| manifest.get("package").and_then(|package| package.get("name")))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| "Cargo.toml 中缺少 package.name".to_string())?;
+
+    let file_name = if cfg!(target_os = "windows") { format!("{}.exe", bin_name) } else { bin_name.to_string() };
+    let binary = dir.join("target").join("release").join(file_name);
+    if !binary.exists() {
+        return Err(format!("编译产物不存在: {}", binary.to_string_lossy()));
+    }
+
+    Ok(LaunchCommand {
+        command: path_arg(&binary),
+        args: Vec::new(),
+        docker: None,
+    })
+}
+
+// cargo 不在工具注册表中，从 PATH 和 ~/.cargo/bin 中查找
+pub fn which_cargo() -> Result<PathBuf, String> {
+    let name = if cfg!(target_os = "windows") { "cargo.exe" } else { "cargo" };
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .chain(cargo_home.map(|home| home.join("bin")))
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| "未安装 cargo，请先通过 https://rustup.rs 安装 Rust 工具链".to_string())
+}
+
+// 构建本地镜像，作为 docker 类型的服务器运行
+fn install_docker(dir: &Path, name: &str) -> Result<LaunchCommand, String> {
+    let docker = tool_executable("docker")?;
+    let image = format!("omni-mcp/{}", name.to_lowercase());
+    run_in_dir(&docker, &["build", "-t", &image, "."], dir)?;
+
+    Ok(LaunchCommand {
+        command: "docker".to_string(),
+        args: Vec::new(),
+        docker: Some(DockerConfig {
+            image,
+            ..Default::default()
+        }),
+    })
+}
+
+// 按项目类型安装依赖并确定启动命令
+pub fn install_project(dir: &Path, name: &str) -> Result<(ProjectType, LaunchCommand), String> {
+    let project_type = ProjectType::detect(dir)
+        .ok_or_else(|| "无法识别项目类型，需要 package.json、pyproject.toml、Cargo.toml 或 Dockerfile".to_string())?;
+    info!("{} 项目类型: {:?}", name, project_type);
+
+    let launch = match project_type {
+        ProjectType::Node => install_node(dir)?,
+        ProjectType::Python => install_python(dir)?,
+        ProjectType::Rust => install_rust(dir)?,
+        ProjectType::Docker => install_docker(dir, name)?,
+    };
+    Ok((project_type, launch))
+}
+
+// 仓库地址和版本会作为 git 的参数，以 - 开头时会被当成选项执行
+fn check_git_arg(value: &str, what: &str) -> Result<(), String> {
+    if value.is_empty() || value.starts_with('-') {
+        return Err(format!("{}无效: {}", what, value));
+    }
+    Ok(())
+}
+
+// 把分支、标签或提交解析为提交，分支返回远程分支的最新提交和分支名
+fn resolve_ref(git: &Path, dir: &Path, git_ref: &str) -> Result<(String, Option<String>), String> {
+    check_git_arg(git_ref, "版本")?;
+    let remote_branch = format!("refs/remotes/origin/{}", git_ref);
+    if let Ok(commit) = git_output(git, &["rev-parse", "--verify", "-q", &format!("{}^{{commit}}", remote_branch)], dir) {
+        return Ok((commit, Some(git_ref.to_string())));
+    }
+    let commit = git_output(git, &["rev-parse", "--verify", "-q", &format!("{}^{{commit}}", git_ref)], dir)
+        .map_err(|_| format!("找不到分支、标签或提交: {}", git_ref))?;
+    Ok((commit, None))
+}
+
+// 检出指定版本：分支重置到 origin 上的最新提交并跟踪它，标签和提交以分离头指针检出
+fn checkout_ref(git: &Path, dir: &Path, git_ref: &str) -> Result<(), String> {
+    match resolve_ref(git, dir, git_ref)? {
+        (_, Some(branch)) => {
+            let upstream = format!("origin/{}", branch);
+            run_in_dir(git, &["checkout", "-B", &branch, "--track", &upstream], dir)
+        },
+        (commit, None) => run_in_dir(git, &["checkout", "--detach", &commit], dir),
+    }
+    .map(|_| ())
+}
+
+// 克隆仓库并检出指定的分支、标签或提交
+fn clone_repository(url: &str, git_ref: Option<&str>, dir: &Path) -> Result<(), String> {
+    check_git_arg(url, "仓库地址")?;
+    let git = tool_executable("git")?;
+    let parent = dir.parent().ok_or_else(|| "无效的服务器目录".to_string())?;
+    std::fs::create_dir_all(parent).map_err(|e| format!("创建服务器目录失败: {}", e))?;
+
+    run_in_dir(&git, &["clone", "--", url, &path_arg(dir)], parent)?;
+    if let Some(git_ref) = git_ref {
+        checkout_ref(&git, dir, git_ref)?;
+    }
+    Ok(())
+}
+
+// 执行 git 命令并返回去掉首尾空白的输出
+fn git_output(git: &Path, args: &[&str], dir: &Path) -> Result<String, String> {
+    run_in_dir(git, args, dir).map(|output| output.trim().to_string())
+}
+
+// 从 git 仓库安装 MCP 服务器，返回保存的服务器配置
+pub fn install_from_git(url: &str, git_ref: Option<&str>, name: Option<&str>) -> Result<McpServerConfig, String> {
+    let name = match name {
+        Some(name) => {
+            validate_server_name(name)?;
+            name.to_string()
+        },
+        None => server_name_from_url(url)?,
+    };
+    info!("从 {} 安装 MCP 服务器 {}", url, name);
+
+    if save_mcp_config::count_mcp_server_config(&name).unwrap_or(0) > 0 {
+        return Err(format!("服务器 {} 已存在", name));
+    }
+    let install_dir = get_mcp_servers_root_dir()?.join(&name);
+    let not_empty = std::fs::read_dir(&install_dir)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    if not_empty {
+        return Err(format!("服务器目录 {} 已存在且不为空", install_dir.to_string_lossy()));
+    }
+
+    let result = clone_repository(url, git_ref, &install_dir)
+        .and_then(|_| install_project(&install_dir, &name));
+    let result = result.and_then(|installed| {
+        let commit = git_output(&tool_executable("git")?, &["rev-parse", "HEAD"], &install_dir)?;
+        Ok((installed, commit))
+    });
+    let ((project_type, launch), installed_commit) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            // 安装失败时删除克隆的目录，方便重试
+            if install_dir.exists() {
+                if let Err(remove_err) = std::fs::remove_dir_all(&install_dir) {
+                    warn!("删除 {:?} 失败: {}", install_dir, remove_err);
+                }
+            }
+            return Err(e);
+        },
+    };
+
+    let mut config = McpServerConfig {
+        name: name.clone(),
+        description: Some(format!("从 {} 安装", url)),
+        command: launch.command,
+        args: launch.args,
+        is_active: true,
+        env: None,
+        type_: Some(if launch.docker.is_some() { "docker" } else { "stdio" }.to_string()),
+        base_url: None,
+        install_dir: Some(path_arg(&install_dir)),
+        installed_commit: Some(installed_commit),
+        upgraded_at: None,
+        package: None,
+        docker: launch.docker,
+        lifecycle: None,
+        runtime_status: None,
+    };
+    save_mcp_config::save_mcp_server_config(config.clone())?;
+
+    info!("{} ({:?} 项目) 安装完成: {} {:?}", name, project_type, config.command, config.args);
+    config.runtime_status = save_mcp_config::check_server_runtime(&config);
+    Ok(config)
+}
+
+// 从 git 仓库安装 MCP 服务器
+//
+// 克隆到 ~/.omni-mcp/mcpServer/<name>，根据项目类型用 bun、uv、cargo 或 docker 安装，
+// 并把启动命令和安装目录保存到 mcpServers 表
+#[tauri::command]
+pub async fn install_mcp_server_from_git(
+    url: String,
+    git_ref: Option<String>,
+    name: Option<String>,
+) -> Result<McpServerConfig, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        install_from_git(&url, git_ref.as_deref(), name.as_deref())
+    })
+    .await
+    .map_err(|e| format!("安装任务异常结束: {}", e))?
+}
+
+// 上游的一个提交
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitInfo {
+    pub hash: String,
+    pub author: String,
+    pub date: String,
+    pub summary: String,
+}
+
+// 更新检查结果
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerUpdateInfo {
+    pub name: String,
+    pub installed_commit: String,
+    // 跟踪的上游分支，如 origin/main
+    pub upstream: String,
+    pub upstream_commit: String,
+    // 已安装版本之后的上游提交，按时间倒序
+    pub commits: Vec<CommitInfo>,
+    pub update_available: bool,
+}
+
+// 升级结果
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerUpgradeReport {
+    pub name: String,
+    pub previous_commit: String,
+    pub installed_commit: String,
+    pub commits: Vec<CommitInfo>,
+    pub health: HealthCheckReport,
+}
+
+// 获取 git 安装的服务器目录，非 git 安装的服务器无法升级
+fn git_install_dir(config: &McpServerConfig) -> Result<PathBuf, String> {
+    let dir = config.install_dir.as_ref()
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("服务器 {} 不是从 git 安装的", config.name))?;
+    if !dir.join(".git").exists() {
+        return Err(format!("{} 不是 git 仓库", dir.to_string_lossy()));
+    }
+    Ok(dir)
+}
+
+// 当前检出所跟踪的上游：分支使用其上游分支，标签或提交使用远程默认分支
+fn upstream_ref(git: &Path, dir: &Path) -> Result<String, String> {
+    git_output(git, &["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"], dir)
+        .or_else(|_| git_output(git, &["symbolic-ref", "--short", "refs/remotes/origin/HEAD"], dir))
+        .map_err(|_| "无法确定上游分支，请指定要升级到的分支、标签或提交".to_string())
+}
+
+// 列出 from..to 之间的提交
+fn commits_between(git: &Path, dir: &Path, from: &str, to: &str) -> Result<Vec<CommitInfo>, String> {
+    let range = format!("{}..{}", from, to);
+    let output = git_output(git, &["log", "--format=%H%x1f%an%x1f%aI%x1f%s", &range], dir)?;
+    Ok(output.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(4, '\u{1f}');
+            Some(CommitInfo {
+                hash: parts.next()?.to_string(),
+                author: parts.next()?.to_string(),
+                date: parts.next()?.to_string(),
+                summary: parts.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+// 获取上游更新并列出已安装版本之后的提交
+pub fn check_update(name: &str) -> Result<ServerUpdateInfo, String> {
+    let config = save_mcp_config::get_mcp_server(name)?;
+    let dir = git_install_dir(&config)?;
+    let git = tool_executable("git")?;
+
+    run_in_dir(&git, &["fetch", "--tags", "origin"], &dir)?;
+    let installed_commit = git_output(&git, &["rev-parse", "HEAD"], &dir)?;
+    let upstream = upstream_ref(&git, &dir)?;
+    let upstream_commit = git_output(&git, &["rev-parse", &upstream], &dir)?;
+    let commits = commits_between(&git, &dir, &installed_commit, &upstream_commit)?;
+
+    Ok(ServerUpdateInfo {
+        name: name.to_string(),
+        installed_commit,
+        update_available: !commits.is_empty(),
+        upstream,
+        upstream_commit,
+        commits,
+    })
+}
+
+// 回到升级前的检出，重新安装依赖
+fn rollback(git: &Path, dir: &Path, branch: Option<&str>, previous_commit: &str, name: &str) {
+    let result = match branch {
+        Some(branch) => run_in_dir(git, &["checkout", "-f", branch], dir)
+            .and_then(|_| run_in_dir(git, &["reset", "--hard", previous_commit], dir)),
+        None => run_in_dir(git, &["checkout", "-f", "--detach", previous_commit], dir),
+    };
+    match result.and_then(|_| install_project(dir, name)) {
+        Ok(_) => info!("{} 已回滚到 {}", name, previous_commit),
+        Err(e) => error!("{} 回滚失败: {}", name, e),
+    }
+}
+
+// 原地升级 git 安装的服务器
+//
+// 未指定 git_ref 时升级到上游最新提交；升级后重新安装依赖并做健康检查，
+// 任一步骤失败都会回滚到升级前的提交
+pub fn upgrade(name: &str, git_ref: Option<&str>) -> Result<ServerUpgradeReport, String> {
+    let config = save_mcp_config::get_mcp_server(name)?;
+    let dir = git_install_dir(&config)?;
+    let git = tool_executable("git")?;
+
+    run_in_dir(&git, &["fetch", "--tags", "origin"], &dir)?;
+    let previous_commit = git_output(&git, &["rev-parse", "HEAD"], &dir)?;
+    // 检出的分支，分离头指针时为空
+    let branch = git_output(&git, &["symbolic-ref", "-q", "--short", "HEAD"], &dir).ok();
+
+    let target = match git_ref {
+        Some(git_ref) => {
+            check_git_arg(git_ref, "版本")?;
+            git_ref.to_string()
+        },
+        None => upstream_ref(&git, &dir)?,
+    };
+    info!("升级 {}: {} -> {}", name, previous_commit, target);
+
+    let checkout = match (git_ref, &branch) {
+        // 分支跟随上游时快进，保持在分支上
+        (None, Some(_)) => run_in_dir(&git, &["merge", "--ff-only", &target], &dir).map(|_| ()),
+        (None, None) => run_in_dir(&git, &["checkout", "--detach", &target], &dir).map(|_| ()),
+        (Some(git_ref), _) => checkout_ref(&git, &dir, git_ref),
+    };
+    if let Err(e) = checkout {
+        rollback(&git, &dir, branch.as_deref(), &previous_commit, name);
+        return Err(format!("检出 {} 失败，已回滚: {}", target, e));
+    }
+
+    let result = git_output(&git, &["rev-parse", "HEAD"], &dir).and_then(|installed_commit| {
+        let (_, launch) = install_project(&dir, name)?;
+        let upgraded = McpServerConfig {
+            command: launch.command,
+            args: launch.args,
+            docker: launch.docker,
+            ..config.clone()
+        };
+        let health = mcp_client::health_check(&upgraded)
+            .map_err(|e| format!("健康检查失败: {}", e))?;
+        Ok((installed_commit, upgraded, health))
+    });
+
+    let (installed_commit, upgraded, health) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            rollback(&git, &dir, branch.as_deref(), &previous_commit, name);
+            return Err(format!("升级 {} 失败，已回滚到 {}: {}", name, previous_commit, e));
+        },
+    };
+
+    save_mcp_config::record_server_upgrade(name, &upgraded.command, &upgraded.args, &installed_commit)?;
+    let commits = commits_between(&git, &dir, &previous_commit, &installed_commit).unwrap_or_default();
+    info!("{} 已升级到 {}，包含 {} 个提交", name, installed_commit, commits.len());
+
+    Ok(ServerUpgradeReport {
+        name: name.to_string(),
+        previous_commit,
+        installed_commit,
+        commits,
+        health,
+    })
+}
+
+// 检查 git 安装的服务器是否有更新
+#[tauri::command]
+pub async fn check_mcp_server_update(name: String) -> Result<ServerUpdateInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || check_update(&name))
+        .await
+        .map_err(|e| format!("检查更新任务异常结束: {}", e))?
+}
+
+// 升级 git 安装的服务器，失败时自动回滚
+//
+// 升级期间停止正在运行的服务器，避免替换仍在使用的安装目录，升级后重新启动
+#[tauri::command]
+pub async fn upgrade_mcp_server(app: AppHandle, name: String, git_ref: Option<String>) -> Result<ServerUpgradeReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<McpSupervisor>().with_server_stopped(&app, &name, || upgrade(&name, git_ref.as_deref()))?
+    })
+    .await
+    .map_err(|e| format!("升级任务异常结束: {}", e))?
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_env::TestEnv;
+
+    // 最小的 stdio MCP 服务器，serverInfo.version 为 VERSION
+    const SERVER_SOURCE: &str = r##"use std::io::{BufRead, Write};
+
+fn main() {
+    let mut stdout = std::io::stdout();
+    for line in std::io::stdin().lock().lines() {
+        let line = line.unwrap();
+        let Some(start) = line.find("\"id\":") else { continue };
+        let id: String = line[start + 5..].chars().take_while(|c| *c != ',' && *c != '}').collect();
+        let result = if line.contains("\"initialize\"") {
+            r#"{"protocolVersion":"2025-06-18","capabilities":{"tools":{}},"serverInfo":{"name":"test","version":"VERSION"}}"#
+        } else if line.contains("\"tools/list\"") {
+            r#"{"tools":[{"name":"echo","inputSchema":{"type":"object"}}]}"#
+        } else {
+            "{}"
+        };
+        writeln!(stdout, "{{\"jsonrpc\":\"2.0\",\"id\":{},\"result\":{}}}", id.trim(), result).unwrap();
+        stdout.flush().unwrap();
+    }
+}
+"##;
+
+    fn git(args: &[&str], dir: &Path) -> String {
+        let git = tool_executable("git").unwrap();
+        git_output(&git, args, dir).unwrap()
+    }
+
+    // 在工作目录提交服务器源码并推送到裸仓库，返回提交
+    fn commit_server(work: &Path, remote: &Path, main_rs: &str, message: &str) -> String {
+        std::fs::create_dir_all(work.join("src")).unwrap();
+        std::fs::write(
+            work.join("Cargo.toml"),
+            "[package]\nname = \"test-server\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        ).unwrap();
+        std::fs::write(work.join(".gitignore"), "target\n").unwrap();
+        std::fs::write(work.join("src").join("main.rs"), main_rs).unwrap();
+        git(&["add", "-A"], work);
+        git(&["commit", "-q", "-m", message], work);
+        git(&["push", "-q", &path_arg(remote), "HEAD:refs/heads/main"], work);
+        git(&["rev-parse", "HEAD"], work)
+    }
+
+    // 隔离 HOME 和数据目录，创建裸仓库并提交第一个版本，返回仓库地址、工作目录和提交
+    fn setup_repository(env: &mut TestEnv, root: &Path) -> (String, PathBuf, String) {
+        std::fs::remove_dir_all(root).ok();
+        env.isolate_home(root);
+        // 编译产物需要在安装目录的 target 下
+        env.remove("CARGO_TARGET_DIR");
+        for key in ["GIT_AUTHOR_NAME", "GIT_COMMITTER_NAME"] {
+            env.set(key, "test");
+        }
+        for key in ["GIT_AUTHOR_EMAIL", "GIT_COMMITTER_EMAIL"] {
+            env.set(key, "test@example.com");
+        }
+
+        let remote = root.join("remote.git");
+        let work = root.join("work");
+        std::fs::create_dir_all(&remote).unwrap();
+        std::fs::create_dir_all(&work).unwrap();
+        git(&["init", "-q", "--bare"], &remote);
+        git(&["symbolic-ref", "HEAD", "refs/heads/main"], &remote);
+        git(&["init", "-q"], &work);
+        let first = commit_server(&work, &remote, &SERVER_SOURCE.replace("VERSION", "1.0.0"), "v1");
+        (path_arg(&remote), work, first)
+    }
+
+    fn test_root(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("omni-mcp-{}-{}", name, std::process::id()))
+    }
+
+    fn has_git_and_cargo() -> bool {
+        let available = which_cargo().is_ok() && tool_executable("git").is_ok();
+        if !available {
+            eprintln!("跳过: 需要 git 和 cargo");
+        }
+        available
+    }
+
+    #[test]
+    fn install_from_bare_repository_rejects_option_injection() {
+        let mut env = TestEnv::lock();
+        if !has_git_and_cargo() {
+            return;
+        }
+        let root = test_root("git-install-test");
+        let (url, _, first) = setup_repository(&mut env, &root);
+
+        // 以 - 开头的地址和版本不会传给 git
+        assert!(install_from_git("--upload-pack=touch pwned", None, Some("evil")).is_err());
+        assert!(install_from_git(&url, Some("--output=pwned"), Some("evil")).is_err());
+
+        let config = install_from_git(&url, None, Some("test-server")).unwrap();
+        assert_eq!(config.installed_commit.as_deref(), Some(first.as_str()));
+        let install_dir = PathBuf::from(config.install_dir.unwrap());
+        assert!(Path::new(&config.command).starts_with(&install_dir));
+        assert!(upgrade("test-server", Some("--output=pwned")).is_err());
+        assert!(!install_dir.join("pwned").exists());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn upgrade_and_rollback_from_bare_repository() {
+        let mut env = TestEnv::lock();
+        if !has_git_and_cargo() {
+            return;
+        }
+        let root = test_root("git-upgrade-test");
+        let (url, work, first) = setup_repository(&mut env, &root);
+        let remote = root.join("remote.git");
+        let config = install_from_git(&url, None, Some("test-server")).unwrap();
+        let install_dir = PathBuf::from(config.install_dir.unwrap());
+
+        // 快进到上游的新提交
+        let second = commit_server(&work, &remote, &SERVER_SOURCE.replace("VERSION", "2.0.0"), "v2");
+        let report = upgrade("test-server", None).unwrap();
+        assert_eq!(report.previous_commit, first);
+        assert_eq!(report.installed_commit, second);
+        assert_eq!(report.commits.len(), 1);
+        assert_eq!(report.health.server_version.as_deref(), Some("2.0.0"));
+
+        // 新版本无法通过健康检查时回滚
+        commit_server(&work, &remote, "fn main() {}\n", "broken");
+        assert!(upgrade("test-server", None).is_err());
+        assert_eq!(git(&["rev-parse", "HEAD"], &install_dir), second);
+        let saved = save_mcp_config::get_mcp_server("test-server").unwrap();
+        assert_eq!(saved.installed_commit.as_deref(), Some(second.as_str()));
+
+        // 上游改写历史后不能快进，保持当前提交
+        git(&["reset", "-q", "--hard", &first], &work);
+        std::fs::write(work.join("README.md"), "rewritten\n").unwrap();
+        git(&["add", "-A"], &work);
+        git(&["commit", "-q", "-m", "rewritten"], &work);
+        git(&["push", "-q", "--force", &url, "HEAD:refs/heads/main"], &work);
+        assert!(upgrade("test-server", None).is_err());
+        assert_eq!(git(&["rev-parse", "HEAD"], &install_dir), second);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::mcp_health::HealthMonitor;
+use crate::mcp_supervisor::{self, McpSupervisor};
+use crate::save_mcp_config::{self, McpServerConfig};
+
+// 检查生命周期的间隔
+const ENFORCE_INTERVAL: Duration = Duration::from_secs(30);
+// 常驻服务器反复退出时，两次自动重启之间的最长间隔
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30 * 60);
+
+// 服务器的生命周期，未设置时按需启动
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum Lifecycle {
+    // 应用启动时启动，退出后自动重启
+    Always,
+    // 第一次调用工具时启动，启动前使用缓存的工具列表
+    OnDemand,
+    // 按需启动，超过 minutes 分钟未使用后停止
+    IdleTimeout { minutes: u64 },
+}
+
+impl Lifecycle {
+    pub fn of(config: &McpServerConfig) -> Lifecycle {
+        config.lifecycle.clone().unwrap_or(Lifecycle::OnDemand)
+    }
+}
+
+// 解析 mcpServers 中的 lifecycle 字段，支持 "always" 这样的简写和 {"mode": "idle-timeout", "minutes": 10}
+pub fn parse_lifecycle(value: &Value) -> Result<Lifecycle, String> {
+    let value = match value.as_str() {
+        Some(mode) => serde_json::json!({ "mode": mode }),
+        None => value.clone(),
+    };
+    serde_json::from_value(value).map_err(|e| format!("lifecycle 设置无效: {}", e))
+}
+
+// 第 attempts 次自动重启前需要等待的时间，从检查间隔开始每次翻倍
+fn restart_delay(attempts: u32) -> u64 {
+    ENFORCE_INTERVAL.as_secs()
+        .saturating_mul(1 << attempts.min(16))
+        .min(MAX_RESTART_DELAY.as_secs())
+}
+
+// 启动 always 服务器并重启已退出的，停止空闲超时的服务器
+fn enforce(app: &AppHandle) {
+    let servers = match tauri::async_runtime::block_on(save_mcp_config::get_all_mcp_servers(Some(true))) {
+        Ok(servers) => servers,
+        Err(e) => {
+            warn!("读取服务器配置失败: {}", e);
+            return;
+        },
+    };
+    let supervisor = app.state::<McpSupervisor>();
+    let monitor = app.state::<HealthMonitor>();
+    let now = mcp_supervisor::unix_now();
+
+    // SSE 服务器不由应用启动
+    for server in servers.iter().filter(|server| server.type_.as_deref() != Some("sse")) {
+        match Lifecycle::of(server) {
+            Lifecycle::Always => {
+                if supervisor.running_client(&server.name).is_some() {
+                    continue;
+                }
+                // 运行时间超过等待时间的服务器退出后立即重启，反复崩溃的逐渐拉长间隔
+                let (attempts, last_start) = monitor.restart_attempts(&server.name);
+                if last_start.is_some_and(|started| now.saturating_sub(started) < restart_delay(attempts)) {
+                    continue;
+                }
+                info!("启动常驻服务器 {}", server.name);
+                if let Err(e) = supervisor.start(app, &server.name) {
+                    warn!("启动服务器 {} 失败: {}", server.name, e);
+                    monitor.record_start_failure(&server.name);
+                }
+            },
+            Lifecycle::IdleTimeout { minutes } => {
+                let Some(idle_secs) = supervisor.idle_secs(&server.name, now) else { continue };
+                if idle_secs >= minutes.saturating_mul(60) {
+                    info!("服务器 {} 已空闲 {} 秒，停止运行", server.name, idle_secs);
+                    if let Err(e) = supervisor.stop(&server.name) {
+                        warn!("{}", e);
+                    }
+                }
+            },
+            Lifecycle::OnDemand => {},
+        }
+    }
+}
+
+// 启动后台线程按生命周期管理服务器
+pub fn spawn_lifecycle(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        enforce(&app);
+        std::thread::sleep(ENFORCE_INTERVAL);
+    });
+}
+
+// 修改服务器的生命周期，立即生效
+#[tauri::command]
+pub async fn set_mcp_server_lifecycle(app: AppHandle, name: String, lifecycle: Lifecycle) -> Result<(), String> {
+    if let Lifecycle::IdleTimeout { minutes: 0 } = lifecycle {
+        return Err("空闲超时必须大于 0 分钟".to_string());
+    }
+    save_mcp_config::update_server_lifecycle(&name, &lifecycle)?;
+    info!("服务器 {} 的生命周期设置为 {:?}", name, lifecycle);
+    tauri::async_runtime::spawn_blocking(move || enforce(&app))
+        .await
+        .map_err(|e| format!("应用生命周期设置异常结束: {}", e))
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::mcp_supervisor::McpSupervisor;
+use crate::save_mcp_config::{self, McpServerConfig};
+
+// 提示词模板的参数
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+// 服务器提供的提示词模板，在聊天中作为 /server:name 使用
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptInfo {
+    pub server: String,
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub arguments: Vec<PromptArgument>,
+}
+
+// 渲染后的聊天消息
+#[derive(Serialize, Debug)]
+pub struct PromptMessage {
+    pub role: String,
+    pub content: String,
+}
+
+fn prompt_info(server: &str, prompt: &Value) -> Option<PromptInfo> {
+    let text = |key: &str| prompt.get(key).and_then(|value| value.as_str()).map(String::from);
+    Some(PromptInfo {
+        server: server.to_string(),
+        name: text("name")?,
+        title: text("title"),
+        description: text("description"),
+        arguments: prompt.get("arguments")
+            .and_then(|arguments| serde_json::from_value(arguments.clone()).ok())
+            .unwrap_or_default(),
+    })
+}
+
+// 把 prompts/get 返回的内容块转换为文本，图片和音频只保留说明
+fn content_to_text(content: &Value) -> String {
+    match content.get("type").and_then(|kind| kind.as_str()) {
+        Some("text") => content.get("text").and_then(|text| text.as_str()).unwrap_or_default().to_string(),
+        Some("resource") => {
+            let resource = content.get("resource").cloned().unwrap_or(Value::Null);
+            let uri = resource.get("uri").and_then(|uri| uri.as_str()).unwrap_or_default();
+            match resource.get("text").and_then(|text| text.as_str()) {
+                Some(text) => format!("<resource uri=\"{}\">\n{}\n</resource>", uri, text),
+                None => format!("[二进制资源 {}]", uri),
+            }
+        },
+        Some("resource_link") => {
+            let uri = content.get("uri").and_then(|uri| uri.as_str()).unwrap_or_default();
+            format!("[资源链接 {}]", uri)
+        },
+        Some(kind) => {
+            let mime_type = content.get("mimeType").and_then(|mime| mime.as_str()).unwrap_or(kind);
+            format!("[{} 内容 ({})]", kind, mime_type)
+        },
+        None => String::new(),
+    }
+}
+
+// 列出正在运行的 stdio 服务器提供的提示词模板，不会为此启动没有运行的服务器
+pub fn list_prompts(app: &AppHandle, servers: &[McpServerConfig]) -> Vec<PromptInfo> {
+    let supervisor = app.state::<McpSupervisor>();
+    // 各服务器的请求互不依赖，并行执行
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = servers.iter()
+            .filter(|server| server.type_.as_deref() != Some("sse"))
+            .filter_map(|server| supervisor.running_client(&server.name).map(|client| (server, client)))
+            .map(|(server, client)| {
+                scope.spawn(move || {
+                    match client.list_prompts() {
+                        Ok(prompts) => prompts.iter()
+                            .filter_map(|prompt| prompt_info(&server.name, prompt))
+                            .collect(),
+                        Err(e) => {
+                            warn!("获取服务器 {} 的提示词失败: {}", server.name, e);
+                            Vec::new()
+                        },
+                    }
+                })
+            })
+            .collect();
+        handles.into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    })
+}
+
+// 用参数渲染提示词模板，必填参数由服务器在 prompts/get 中校验
+pub fn render_prompt(
+    app: &AppHandle,
+    server: &str,
+    name: &str,
+    arguments: HashMap<String, String>,
+) -> Result<Vec<PromptMessage>, String> {
+    let client = app.state::<McpSupervisor>().client(app, server)?;
+    info!("渲染提示词 {} ({})", name, server);
+    let result = client.get_prompt(name, &arguments)?;
+    Ok(result.get("messages")
+        .and_then(|messages| messages.as_array())
+        .map(|messages| messages.iter()
+            .map(|message| PromptMessage {
+                role: message.get("role").and_then(|role| role.as_str()).unwrap_or("user").to_string(),
+                content: message.get("content").map(content_to_text).unwrap_or_default(),
+            })
+            .collect())
+        .unwrap_or_default())
+}
+
+// 列出所有启用且正在运行的服务器的提示词模板及其参数
+#[tauri::command]
+pub async fn list_mcp_prompts(app: AppHandle) -> Result<Vec<PromptInfo>, String> {
+    let servers = save_mcp_config::get_all_mcp_servers(Some(true)).await?;
+    tauri::async_runtime::spawn_blocking(move || list_prompts(&app, &servers))
+        .await
+        .map_err(|e| format!("获取提示词异常结束: {}", e))
+}
+
+// 渲染提示词模板为聊天消息
+#[tauri::command]
+pub async fn get_mcp_prompt(
+    app: AppHandle,
+    server: String,
+    name: String,
+    arguments: Option<HashMap<String, String>>,
+) -> Result<Vec<PromptMessage>, String> {
+    tauri::async_runtime::spawn_blocking(move || render_prompt(&app, &server, &name, arguments.unwrap_or_default()))
+        .await
+        .map_err(|e| format!("渲染提示词异常结束: {}", e))?
+}
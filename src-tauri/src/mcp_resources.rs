@@ -0,0 +1,100 @@
+use log::info;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::mcp_client::McpClient;
+use crate::mcp_supervisor::McpSupervisor;
+
+// 附加到聊天消息中的资源内容
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceAttachment {
+    pub server: String,
+    pub uri: String,
+    pub mime_type: Option<String>,
+    // 作为上下文发送给模型的文本
+    pub text: String,
+}
+
+// 把 resources/read 返回的内容整理为文本，二进制内容只保留说明
+fn contents_to_text(contents: &[Value]) -> String {
+    contents.iter()
+        .map(|content| {
+            let uri = content.get("uri").and_then(|uri| uri.as_str()).unwrap_or_default();
+            match content.get("text").and_then(|text| text.as_str()) {
+                Some(text) => text.to_string(),
+                None => {
+                    let mime_type = content.get("mimeType").and_then(|mime| mime.as_str()).unwrap_or("application/octet-stream");
+                    let size = content.get("blob").and_then(|blob| blob.as_str()).map(|blob| blob.len() / 4 * 3).unwrap_or_default();
+                    format!("[二进制资源 {} ({}, 约 {} 字节)]", uri, mime_type, size)
+                },
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// 在后台线程中获取服务器的客户端并执行操作
+async fn with_client<T, F>(app: AppHandle, server: String, action: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&McpClient) -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || {
+        let client = app.state::<McpSupervisor>().client(&app, &server)?;
+        action(&client)
+    })
+    .await
+    .map_err(|e| format!("资源请求异常结束: {}", e))?
+}
+
+// 列出服务器提供的资源，服务器没有运行时会先启动
+#[tauri::command]
+pub async fn list_mcp_resources(app: AppHandle, server: String) -> Result<Vec<Value>, String> {
+    with_client(app, server, |client| client.list_resources()).await
+}
+
+// 列出服务器提供的资源模板
+#[tauri::command]
+pub async fn list_mcp_resource_templates(app: AppHandle, server: String) -> Result<Vec<Value>, String> {
+    with_client(app, server, |client| client.list_resource_templates()).await
+}
+
+// 读取资源，返回原始的 contents
+#[tauri::command]
+pub async fn read_mcp_resource(app: AppHandle, server: String, uri: String) -> Result<Vec<Value>, String> {
+    with_client(app, server, move |client| client.read_resource(&uri)).await
+}
+
+// 订阅资源，变更通过 mcp-resource-updated 事件通知前端
+#[tauri::command]
+pub async fn subscribe_mcp_resource(app: AppHandle, server: String, uri: String) -> Result<(), String> {
+    info!("订阅资源 {} ({})", uri, server);
+    with_client(app, server, move |client| client.subscribe_resource(&uri)).await
+}
+
+#[tauri::command]
+pub async fn unsubscribe_mcp_resource(app: AppHandle, server: String, uri: String) -> Result<(), String> {
+    info!("取消订阅资源 {} ({})", uri, server);
+    with_client(app, server, move |client| client.unsubscribe_resource(&uri)).await
+}
+
+// 读取资源并整理为可以附加到聊天消息中的文本
+#[tauri::command]
+pub async fn attach_mcp_resource(app: AppHandle, server: String, uri: String) -> Result<ResourceAttachment, String> {
+    let name = server.clone();
+    with_client(app, server, move |client| {
+        let contents = client.read_resource(&uri)?;
+        let mime_type = contents.first()
+            .and_then(|content| content.get("mimeType"))
+            .and_then(|mime| mime.as_str())
+            .map(String::from);
+        Ok(ResourceAttachment {
+            server: name,
+            text: contents_to_text(&contents),
+            uri,
+            mime_type,
+        })
+    }).await
+}
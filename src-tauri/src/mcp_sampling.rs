@@ -0,0 +1,264 @@
+use std::io::Write;
+use std::process::Stdio;
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::mcp_client::JsonRpcError;
+use crate::model_config::{self, ModelConfig};
+use crate::pending_requests::PendingRequests;
+use crate::tools;
+
+// 前端监听的采样请求事件，用户确认后通过 respond_sampling_request 回复
+pub const SAMPLING_REQUEST_EVENT: &str = "mcp-sampling-request";
+// 等待用户确认的时间
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(600);
+// 模型生成的超时时间（秒）
+const COMPLETION_TIMEOUT_SECS: &str = "300";
+// 服务器没有指定时的最大 token 数
+const DEFAULT_MAX_TOKENS: u64 = 1024;
+
+// 采样请求中的消息，非文本内容只保留说明
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SamplingMessage {
+    pub role: String,
+    pub content: String,
+}
+
+// 可选的模型，provider 对应 model_configs 中的配置
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModelChoice {
+    pub provider: String,
+    pub model: String,
+}
+
+// 发送给前端确认的采样请求
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingRequest {
+    pub id: String,
+    pub server: String,
+    pub messages: Vec<SamplingMessage>,
+    pub system_prompt: Option<String>,
+    pub max_tokens: u64,
+    pub temperature: Option<f64>,
+    pub stop_sequences: Vec<String>,
+    // 服务器的模型偏好中给出的模型名称提示
+    pub hints: Vec<String>,
+    // 根据提示选出的模型
+    pub selected: Option<ModelChoice>,
+    pub candidates: Vec<ModelChoice>,
+}
+
+// 用户的处理结果，可以修改模型、消息、系统提示词和最大 token 数
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingDecision {
+    pub approved: bool,
+    #[serde(default)]
+    pub model: Option<ModelChoice>,
+    #[serde(default)]
+    pub messages: Option<Vec<SamplingMessage>>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+}
+
+// 等待用户确认的采样请求
+pub type SamplingRequests = PendingRequests<SamplingDecision>;
+
+fn rejected(message: &str) -> JsonRpcError {
+    JsonRpcError { code: -1, message: message.to_string() }
+}
+
+fn content_text(content: &Value) -> String {
+    match content.get("type").and_then(|kind| kind.as_str()) {
+        Some("text") => content.get("text").and_then(|text| text.as_str()).unwrap_or_default().to_string(),
+        Some(kind) => {
+            let mime_type = content.get("mimeType").and_then(|mime| mime.as_str()).unwrap_or(kind);
+            format!("[{} 内容 ({})]", kind, mime_type)
+        },
+        None => String::new(),
+    }
+}
+
+// model 字段可以用逗号分隔多个模型
+fn model_candidates(configs: &[ModelConfig]) -> Vec<ModelChoice> {
+    configs.iter()
+        .flat_map(|config| config.model.split(',')
+            .map(|model| model.trim())
+            .filter(|model| !model.is_empty())
+            .map(|model| ModelChoice { provider: config.provider.clone(), model: model.to_string() }))
+        .collect()
+}
+
+// 按提示的顺序匹配模型名称或提供商，都不匹配时使用第一个模型
+fn choose_model(candidates: &[ModelChoice], hints: &[String]) -> Option<ModelChoice> {
+    hints.iter()
+        .map(|hint| hint.to_lowercase())
+        .find_map(|hint| candidates.iter().find(|choice| {
+            choice.model.to_lowercase().contains(&hint) || choice.provider.to_lowercase().contains(&hint)
+        }))
+        .or_else(|| candidates.first())
+        .cloned()
+}
+
+// curl 配置文件中的字符串
+fn curl_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// 通过模型配置的 OpenAI 兼容接口生成回复，密钥和请求体经 stdin 传给 curl，不出现在进程列表中
+fn complete(config: &ModelConfig, model: &str, request: &SamplingRequest) -> Result<String, String> {
+    let mut messages = Vec::new();
+    if let Some(system_prompt) = request.system_prompt.as_deref().filter(|prompt| !prompt.is_empty()) {
+        messages.push(json!({ "role": "system", "content": system_prompt }));
+    }
+    messages.extend(request.messages.iter().map(|message| json!({ "role": message.role, "content": message.content })));
+
+    let mut body = json!({
+        "model": model,
+        "messages": messages,
+        "max_tokens": request.max_tokens,
+        "stream": false,
+    });
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if !request.stop_sequences.is_empty() {
+        body["stop"] = json!(request.stop_sequences);
+    }
+
+    let mut curl_config = vec![format!("header = {}", curl_quote("Content-Type: application/json"))];
+    if !config.session_key.is_empty() {
+        curl_config.push(format!("header = {}", curl_quote(&format!("Authorization: Bearer {}", config.session_key))));
+    }
+    curl_config.push(format!("data-binary = {}", curl_quote(&body.to_string())));
+
+    let method = config.method.as_deref().filter(|method| !method.is_empty()).unwrap_or("/v1/chat/completions");
+    let url = format!("{}{}", config.api_url, method);
+    let mut child = tools::curl_command(&url)
+        .args(["-K", "-", "--max-time", COMPLETION_TIMEOUT_SECS])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("执行 curl 失败: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(curl_config.join("\n").as_bytes())
+            .map_err(|e| format!("发送请求失败: {}", e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| format!("等待模型响应失败: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("请求模型失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let response: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("解析模型响应失败: {}", e))?;
+    // OpenAI 兼容接口返回 choices，Ollama 的 /api/chat 返回 message
+    response.pointer("/choices/0/message/content")
+        .or_else(|| response.pointer("/message/content"))
+        .and_then(|content| content.as_str())
+        .map(String::from)
+        .ok_or_else(|| {
+            let error = response.pointer("/error/message").or_else(|| response.get("error"));
+            format!("模型响应中没有内容: {}", error.map(|error| error.to_string()).unwrap_or_else(|| response.to_string()))
+        })
+}
+
+// 处理服务器的 sampling/createMessage 请求：交给用户确认后调用模型，把回复返回给服务器
+pub fn handle_create_message(app: &AppHandle, server: &str, params: Option<Value>) -> Result<Value, JsonRpcError> {
+    let params = params.unwrap_or(Value::Null);
+    let internal = |message: String| JsonRpcError { code: -32603, message };
+
+    let configs = model_config::load_model_configs().map_err(internal)?;
+    let candidates = model_candidates(&configs);
+    if candidates.is_empty() {
+        return Err(internal("没有配置可用的模型".to_string()));
+    }
+
+    let hints: Vec<String> = params.pointer("/modelPreferences/hints")
+        .and_then(|hints| hints.as_array())
+        .map(|hints| hints.iter()
+            .filter_map(|hint| hint.get("name").and_then(|name| name.as_str()).map(String::from))
+            .collect())
+        .unwrap_or_default();
+    let messages = params.get("messages")
+        .and_then(|messages| messages.as_array())
+        .map(|messages| messages.iter()
+            .map(|message| SamplingMessage {
+                role: message.get("role").and_then(|role| role.as_str()).unwrap_or("user").to_string(),
+                content: message.get("content").map(content_text).unwrap_or_default(),
+            })
+            .collect())
+        .unwrap_or_default();
+
+    let requests = app.state::<SamplingRequests>();
+    let (id, receiver) = requests.register("sampling").map_err(internal)?;
+    let mut request = SamplingRequest {
+        id: id.clone(),
+        server: server.to_string(),
+        messages,
+        system_prompt: params.get("systemPrompt").and_then(|prompt| prompt.as_str()).map(String::from),
+        max_tokens: params.get("maxTokens").and_then(|tokens| tokens.as_u64()).unwrap_or(DEFAULT_MAX_TOKENS),
+        temperature: params.get("temperature").and_then(|temperature| temperature.as_f64()),
+        stop_sequences: params.get("stopSequences")
+            .and_then(|stop| serde_json::from_value(stop.clone()).ok())
+            .unwrap_or_default(),
+        selected: choose_model(&candidates, &hints),
+        hints,
+        candidates,
+    };
+
+    info!("服务器 {} 请求采样: {}", server, id);
+    if let Err(e) = app.emit(SAMPLING_REQUEST_EVENT, &request) {
+        requests.remove(&id);
+        return Err(internal(format!("发送采样请求事件失败: {}", e)));
+    }
+    let Some(decision) = requests.wait(&id, receiver, APPROVAL_TIMEOUT) else {
+        warn!("采样请求 {} 等待确认超时", id);
+        return Err(rejected("等待用户确认超时"));
+    };
+    if !decision.approved {
+        info!("用户拒绝了采样请求 {}", id);
+        return Err(rejected("用户拒绝了采样请求"));
+    }
+
+    if let Some(messages) = decision.messages {
+        request.messages = messages;
+    }
+    if decision.system_prompt.is_some() {
+        request.system_prompt = decision.system_prompt;
+    }
+    if let Some(max_tokens) = decision.max_tokens {
+        request.max_tokens = max_tokens;
+    }
+    let choice = decision.model.or(request.selected.clone())
+        .ok_or_else(|| internal("没有选择模型".to_string()))?;
+    let config = configs.iter()
+        .find(|config| config.provider == choice.provider)
+        .ok_or_else(|| internal(format!("模型配置不存在: {}", choice.provider)))?;
+
+    let text = complete(config, &choice.model, &request).map_err(internal)?;
+    info!("采样请求 {} 已完成 ({}/{})", id, choice.provider, choice.model);
+    Ok(json!({
+        "role": "assistant",
+        "content": { "type": "text", "text": text },
+        "model": choice.model,
+        "stopReason": "endTurn",
+    }))
+}
+
+// 回复服务器的采样请求
+#[tauri::command]
+pub fn respond_sampling_request(
+    requests: State<'_, SamplingRequests>,
+    id: String,
+    decision: SamplingDecision,
+) -> Result<(), String> {
+    requests.respond(&id, decision)
+}
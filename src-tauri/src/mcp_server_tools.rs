@@ -0,0 +1,217 @@
+use std::collections::{HashMap, HashSet};
+
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::AppHandle;
+
+use crate::mcp_tool_cache;
+use crate::mcp_tool_calls;
+use crate::save_mcp_config;
+use crate::sqlite_db::Database;
+
+// 对模型公开的工具名称中服务器和工具之间的分隔符，如 github__search
+pub const NAMESPACE_SEPARATOR: &str = "__";
+
+// 服务器工具的设置，与缓存的工具列表合并后返回给前端
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerTool {
+    pub server: String,
+    pub tool: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub alias: Option<String>,
+    // 对模型公开的名称
+    pub qualified_name: String,
+}
+
+// 提供给模型的工具定义，name 在所有服务器之间唯一
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentTool {
+    pub name: String,
+    pub server: String,
+    pub tool: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+struct ToolSetting {
+    enabled: bool,
+    alias: Option<String>,
+}
+
+fn get_db() -> Result<Database, String> {
+    let db = Database::new().map_err(|e| format!("初始化数据库失败: {}", e))?;
+    db.init_server_tools_table().map_err(|e| format!("初始化表失败: {}", e))?;
+    Ok(db)
+}
+
+// 模型接口只接受字母、数字、下划线和连字符
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+pub fn qualified_name(server: &str, tool: &str, alias: Option<&str>) -> String {
+    format!("{}{}{}", sanitize(server), NAMESPACE_SEPARATOR, sanitize(alias.unwrap_or(tool)))
+}
+
+fn load_settings(server: &str) -> Result<HashMap<String, ToolSetting>, String> {
+    let db = get_db()?;
+    let conn = db.get_connection();
+    let mut stmt = conn.prepare("SELECT tool, enabled, alias FROM server_tools WHERE server = ?1")
+        .map_err(|e| format!("准备查询语句失败: {}", e))?;
+    let settings = stmt.query_map([server], |row| {
+        let enabled: i64 = row.get(1)?;
+        Ok((row.get::<_, String>(0)?, ToolSetting { enabled: enabled != 0, alias: row.get(2)? }))
+    })
+    .map_err(|e| format!("查询工具设置失败: {}", e))?
+    .filter_map(|setting| setting.ok())
+    .collect();
+    Ok(settings)
+}
+
+fn tool_name(tool: &Value) -> Option<&str> {
+    tool.get("name").and_then(|name| name.as_str())
+}
+
+// 服务器的工具及其设置，工具来自缓存的工具列表
+pub fn list_server_tools(server: &str) -> Result<Vec<ServerTool>, String> {
+    let settings = load_settings(server)?;
+    let cached = mcp_tool_cache::cached_tools(Some(server))?;
+    let tools = cached.first().map(|cached| cached.tools.as_slice()).unwrap_or_default();
+
+    Ok(tools.iter()
+        .filter_map(|tool| {
+            let name = tool_name(tool)?;
+            let setting = settings.get(name);
+            let alias = setting.and_then(|setting| setting.alias.clone());
+            Some(ServerTool {
+                server: server.to_string(),
+                tool: name.to_string(),
+                description: tool.get("description").and_then(|d| d.as_str()).map(String::from),
+                enabled: setting.is_none_or(|setting| setting.enabled),
+                qualified_name: qualified_name(server, name, alias.as_deref()),
+                alias,
+            })
+        })
+        .collect())
+}
+
+// 按别名或原名查找服务器的工具，返回原名；工具被禁用时返回错误
+pub fn resolve_tool(server: &str, name: &str) -> Result<String, String> {
+    let settings = load_settings(server)?;
+    let tool = settings.iter()
+        .find(|(_, setting)| setting.alias.as_deref() == Some(name))
+        .map(|(tool, _)| tool.clone())
+        .unwrap_or_else(|| name.to_string());
+    if settings.get(&tool).is_some_and(|setting| !setting.enabled) {
+        return Err(format!("服务器 {} 的工具 {} 已被禁用", server, tool));
+    }
+    Ok(tool)
+}
+
+// 所有启用服务器中启用的工具，名称按服务器加命名空间
+pub async fn agent_tools() -> Result<Vec<AgentTool>, String> {
+    let servers = save_mcp_config::get_all_mcp_servers(Some(true)).await?;
+    let mut names = HashSet::new();
+    let mut agent_tools = Vec::new();
+
+    for server in servers {
+        let settings = load_settings(&server.name)?;
+        let cached = mcp_tool_cache::cached_tools(Some(&server.name))?;
+        for tool in cached.iter().flat_map(|cached| cached.tools.iter()) {
+            let Some(name) = tool_name(tool) else { continue };
+            let setting = settings.get(name);
+            if setting.is_some_and(|setting| !setting.enabled) {
+                continue;
+            }
+            let qualified = qualified_name(&server.name, name, setting.and_then(|setting| setting.alias.as_deref()));
+            // 服务器名清理后可能重名，重复的名称只保留第一个
+            if !names.insert(qualified.clone()) {
+                warn!("工具名称 {} 重复，已忽略服务器 {} 的工具 {}", qualified, server.name, name);
+                continue;
+            }
+            let description = tool.get("description").and_then(|d| d.as_str()).unwrap_or_default();
+            agent_tools.push(AgentTool {
+                name: qualified,
+                server: server.name.clone(),
+                tool: name.to_string(),
+                description: format!("[{}] {}", server.name, description),
+                input_schema: tool.get("inputSchema").cloned().unwrap_or_else(|| json!({ "type": "object" })),
+            });
+        }
+    }
+    Ok(agent_tools)
+}
+
+// 把对模型公开的名称解析为服务器和工具原名
+pub async fn resolve_agent_tool(name: &str) -> Result<(String, String), String> {
+    agent_tools().await?
+        .into_iter()
+        .find(|tool| tool.name == name)
+        .map(|tool| (tool.server, tool.tool))
+        .ok_or_else(|| format!("工具不存在或已被禁用: {}", name))
+}
+
+// 获取服务器的工具及其启用状态和别名
+#[tauri::command]
+pub fn get_server_tools(server: String) -> Result<Vec<ServerTool>, String> {
+    list_server_tools(&server)
+}
+
+// 设置工具的启用状态和别名，别名为空时使用原名
+#[tauri::command]
+pub fn set_server_tool(server: String, tool: String, enabled: bool, alias: Option<String>) -> Result<Vec<ServerTool>, String> {
+    let alias = alias.map(|alias| alias.trim().to_string()).filter(|alias| !alias.is_empty());
+    if let Some(alias) = &alias {
+        if alias.contains(NAMESPACE_SEPARATOR) || sanitize(alias) != *alias {
+            return Err(format!("别名 {} 只能包含字母、数字、单个下划线和连字符", alias));
+        }
+        // 同一服务器内别名不能与其他工具的名称或别名相同
+        let conflict = list_server_tools(&server)?.into_iter()
+            .any(|other| other.tool != tool && (other.tool == *alias || other.alias.as_deref() == Some(alias.as_str())));
+        if conflict {
+            return Err(format!("服务器 {} 已有名为 {} 的工具", server, alias));
+        }
+    }
+
+    get_db()?.get_connection().execute(
+        "INSERT OR REPLACE INTO server_tools (server, tool, enabled, alias) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![server, tool, enabled as i64, alias],
+    ).map_err(|e| format!("保存工具设置失败: {}", e))?;
+    info!("服务器 {} 的工具 {}: enabled={}, alias={:?}", server, tool, enabled, alias);
+    list_server_tools(&server)
+}
+
+// 获取提供给模型的工具定义，使用 OpenAI function 格式
+#[tauri::command]
+pub async fn get_agent_tools() -> Result<Vec<Value>, String> {
+    Ok(agent_tools().await?
+        .into_iter()
+        .map(|tool| json!({
+            "type": "function",
+            "function": {
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.input_schema,
+            },
+        }))
+        .collect())
+}
+
+// 按命名空间名称调用工具，如 github__search
+#[tauri::command]
+pub async fn call_agent_tool(
+    app: AppHandle,
+    name: String,
+    arguments: Option<Value>,
+    call_id: Option<String>,
+    timeout_secs: Option<u64>,
+) -> Result<Value, String> {
+    let (server, tool) = resolve_agent_tool(&name).await?;
+    mcp_tool_calls::call_mcp_tool(app, server, tool, arguments, call_id, timeout_secs).await
+}
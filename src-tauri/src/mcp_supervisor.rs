@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::docker_runtime;
+use crate::mcp_client::{ClientHandler, JsonRpcError, McpClient};
+use crate::mcp_elicitation;
+use crate::mcp_health;
+use crate::mcp_sampling;
+use crate::mcp_tool_cache;
+use crate::mcp_tool_calls;
+use crate::save_mcp_config;
+use crate::workspace_roots;
+
+// 前端监听的服务器状态事件
+pub const SERVER_STATUS_EVENT: &str = "mcp-server-status";
+// 订阅的资源内容变更
+pub const RESOURCE_UPDATED_EVENT: &str = "mcp-resource-updated";
+// 服务器的资源列表变更
+pub const RESOURCE_LIST_CHANGED_EVENT: &str = "mcp-resource-list-changed";
+// 等待 docker run 写入容器 id 的时间
+const CONTAINER_ID_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 正在运行的服务器
+struct RunningServer {
+    client: Arc<McpClient>,
+    pid: Option<u32>,
+    container_id: Option<String>,
+    started_at: u64,
+    // 最近一次通过 client 使用的时间，Unix 秒
+    last_used: AtomicU64,
+}
+
+// 运行中服务器的状态
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningServerStatus {
+    pub name: String,
+    pub pid: Option<u32>,
+    pub container_id: Option<String>,
+    // 启动时间，Unix 秒
+    pub started_at: u64,
+    pub running: bool,
+}
+
+// 把服务器的通知转发给前端
+struct SupervisorHandler {
+    app: AppHandle,
+}
+
+impl SupervisorHandler {
+    fn emit(&self, event: &str, payload: Value) {
+        if let Err(e) = self.app.emit(event, payload) {
+            warn!("发送 {} 事件失败: {}", event, e);
+        }
+    }
+}
+
+impl ClientHandler for SupervisorHandler {
+    fn capabilities(&self) -> Value {
+        json!({ "sampling": {}, "roots": { "listChanged": true }, "elicitation": {} })
+    }
+
+    fn on_request(&self, server: &str, method: &str, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        match method {
+            "ping" => Ok(json!({})),
+            "sampling/createMessage" => mcp_sampling::handle_create_message(&self.app, server, params),
+            "elicitation/create" => mcp_elicitation::handle_create(&self.app, server, params),
+            "roots/list" => workspace_roots::roots_list_result()
+                .map_err(|message| JsonRpcError { code: -32603, message }),
+            _ => Err(JsonRpcError::method_not_found(method)),
+        }
+    }
+
+    fn on_notification(&self, server: &str, method: &str, params: Option<Value>) {
+        match method {
+            // 读取线程结束说明服务器进程已经退出
+            "$/closed" => {
+                info!("MCP 服务器 {} 已退出", server);
+                self.emit(SERVER_STATUS_EVENT, json!({ "name": server, "running": false }));
+            },
+            "notifications/progress" => mcp_tool_calls::forward_progress(&self.app, server, params),
+            "notifications/resources/updated" => {
+                let uri = params.as_ref().and_then(|params| params.get("uri")).cloned().unwrap_or(Value::Null);
+                self.emit(RESOURCE_UPDATED_EVENT, json!({ "server": server, "uri": uri }));
+            },
+            "notifications/tools/list_changed" => mcp_tool_cache::refresh_in_background(&self.app, server),
+            "notifications/resources/list_changed" => {
+                self.emit(RESOURCE_LIST_CHANGED_EVENT, json!({ "server": server }));
+            },
+            _ => {},
+        }
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+// 管理应用启动的 MCP 服务器进程和容器
+#[derive(Default)]
+pub struct McpSupervisor {
+    servers: Mutex<HashMap<String, Arc<RunningServer>>>,
+    // 每个服务器的启动锁，同一服务器同时只有一个启动过程
+    start_locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl McpSupervisor {
+    fn start_lock(&self, name: &str) -> Result<Arc<Mutex<()>>, String> {
+        let mut locks = self.start_locks.lock().map_err(|e| format!("获取启动锁失败: {}", e))?;
+        Ok(locks.entry(name.to_string()).or_default().clone())
+    }
+
+    // 启动服务器并完成握手，已在运行时直接返回状态
+    pub fn start(&self, app: &AppHandle, name: &str) -> Result<RunningServerStatus, String> {
+        let lock = self.start_lock(name)?;
+        let _guard = lock.lock().map_err(|e| format!("获取启动锁失败: {}", e))?;
+        self.start_locked(app, name)
+    }
+
+    // 在持有启动锁期间停止服务器并执行 f，原来在运行的服务器在 f 结束后重新启动
+    pub fn with_server_stopped<T>(&self, app: &AppHandle, name: &str, f: impl FnOnce() -> T) -> Result<T, String> {
+        let lock = self.start_lock(name)?;
+        let _guard = lock.lock().map_err(|e| format!("获取启动锁失败: {}", e))?;
+
+        let was_running = self.status(name).is_some_and(|status| status.running);
+        if self.status(name).is_some() {
+            self.stop(name)?;
+        }
+        let result = f();
+        if was_running {
+            if let Err(e) = self.start_locked(app, name) {
+                warn!("重新启动服务器 {} 失败: {}", name, e);
+            }
+        }
+        Ok(result)
+    }
+
+    fn start_locked(&self, app: &AppHandle, name: &str) -> Result<RunningServerStatus, String> {
+        if let Some(status) = self.status(name) {
+            if status.running {
+                return Ok(status);
+            }
+            self.stop(name)?;
+        }
+
+        let config = save_mcp_config::get_mcp_server(name)?;
+        let handler = Arc::new(SupervisorHandler { app: app.clone() });
+        // 握手可能耗时较长，不持有锁
+        let client = Arc::new(McpClient::connect(&config, handler)?);
+        let container_id = config.docker.as_ref()
+            .and_then(|_| docker_runtime::read_container_id(name, CONTAINER_ID_TIMEOUT));
+
+        let server = Arc::new(RunningServer {
+            pid: client.pid(),
+            client,
+            container_id,
+            started_at: unix_now(),
+            last_used: AtomicU64::new(unix_now()),
+        });
+
+        {
+            let mut servers = self.servers.lock().map_err(|e| format!("获取运行中的服务器失败: {}", e))?;
+            // 启动期间已有其他实例在运行时，关闭刚启动的进程
+            if let Some(existing) = servers.get(name).filter(|existing| existing.client.is_running()) {
+                server.client.close();
+                if let Some(id) = &server.container_id {
+                    docker_runtime::remove_container(id)?;
+                }
+                return Ok(Self::to_status(name, existing));
+            }
+            servers.insert(name.to_string(), server.clone());
+        }
+        if let Err(e) = save_mcp_config::update_server_process(name, server.pid, server.container_id.as_deref()) {
+            warn!("记录服务器 {} 的进程失败: {}", name, e);
+        }
+        app.state::<mcp_health::HealthMonitor>().record_start(name);
+        info!("MCP 服务器 {} 已启动", name);
+        // 每次启动或重连后刷新工具列表缓存
+        mcp_tool_cache::refresh_in_background(app, name);
+        Ok(Self::to_status(name, &server))
+    }
+
+    // 获取运行中服务器的客户端，没有运行时先启动
+    pub fn client(&self, app: &AppHandle, name: &str) -> Result<Arc<McpClient>, String> {
+        if let Some(server) = self.servers.lock().ok().and_then(|servers| servers.get(name).cloned()) {
+            server.last_used.store(unix_now(), Ordering::SeqCst);
+        }
+        match self.running_client(name) {
+            Some(client) => Ok(client),
+            None => {
+                self.start(app, name)?;
+                self.servers.lock()
+                    .map_err(|e| format!("获取运行中的服务器失败: {}", e))?
+                    .get(name)
+                    .map(|server| server.client.clone())
+                    .ok_or_else(|| format!("服务器 {} 没有在运行", name))
+            },
+        }
+    }
+
+    // 运行中服务器的客户端，不会启动服务器
+    pub fn running_client(&self, name: &str) -> Option<Arc<McpClient>> {
+        self.servers.lock().ok()?
+            .get(name)
+            .filter(|server| server.client.is_running())
+            .map(|server| server.client.clone())
+    }
+
+    // 服务器未使用的秒数，没有运行或仍有请求在执行时返回 None
+    pub fn idle_secs(&self, name: &str, now: u64) -> Option<u64> {
+        let server = self.servers.lock().ok()?.get(name).cloned()?;
+        if !server.client.is_running() || server.client.has_pending_requests() {
+            return None;
+        }
+        Some(now.saturating_sub(server.last_used.load(Ordering::SeqCst)))
+    }
+
+    // 停止服务器，docker 服务器同时删除容器
+    pub fn stop(&self, name: &str) -> Result<(), String> {
+        let server = self.servers.lock()
+            .map_err(|e| format!("获取运行中的服务器失败: {}", e))?
+            .remove(name);
+        let Some(server) = server else {
+            return Err(format!("服务器 {} 没有在运行", name));
+        };
+
+        server.client.close();
+        if let Some(id) = &server.container_id {
+            docker_runtime::remove_container(id)?;
+        }
+        if let Err(e) = save_mcp_config::update_server_process(name, None, None) {
+            warn!("清除服务器 {} 的进程记录失败: {}", name, e);
+        }
+        info!("MCP 服务器 {} 已停止", name);
+        Ok(())
+    }
+
+    // 应用退出时停止全部服务器
+    pub fn stop_all(&self) {
+        let names: Vec<String> = match self.servers.lock() {
+            Ok(servers) => servers.keys().cloned().collect(),
+            Err(_) => return,
+        };
+        for name in names {
+            if let Err(e) = self.stop(&name) {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    // 向所有运行中的服务器发送通知
+    pub fn notify_all(&self, method: &str, params: Value) {
+        let clients: Vec<(String, Arc<McpClient>)> = match self.servers.lock() {
+            Ok(servers) => servers.iter().map(|(name, server)| (name.clone(), server.client.clone())).collect(),
+            Err(_) => return,
+        };
+        for (name, client) in clients {
+            if let Err(e) = client.notify(method, params.clone()) {
+                warn!("向服务器 {} 发送 {} 失败: {}", name, method, e);
+            }
+        }
+    }
+
+    pub fn status(&self, name: &str) -> Option<RunningServerStatus> {
+        let servers = self.servers.lock().ok()?;
+        servers.get(name).map(|server| Self::to_status(name, server))
+    }
+
+    pub fn list(&self) -> Vec<RunningServerStatus> {
+        let Ok(servers) = self.servers.lock() else {
+            return Vec::new();
+        };
+        let mut list: Vec<RunningServerStatus> = servers.iter()
+            .map(|(name, server)| Self::to_status(name, server))
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+
+    fn to_status(name: &str, server: &RunningServer) -> RunningServerStatus {
+        RunningServerStatus {
+            name: name.to_string(),
+            pid: server.pid,
+            container_id: server.container_id.clone(),
+            started_at: server.started_at,
+            running: server.client.is_running(),
+        }
+    }
+}
+
+// 清理上次运行没有正常退出时遗留的容器
+pub fn cleanup_orphans() {
+    let recorded = match save_mcp_config::take_recorded_processes() {
+        Ok(recorded) => recorded,
+        Err(e) => {
+            warn!("读取遗留进程记录失败: {}", e);
+            Vec::new()
+        },
+    };
+    docker_runtime::cleanup_orphaned_containers(&recorded);
+}
+
+// 启动 MCP 服务器，docker 类型的服务器以容器方式运行
+#[tauri::command]
+pub async fn start_mcp_server(app: AppHandle, name: String) -> Result<RunningServerStatus, String> {
+    info!("启动 MCP 服务器: {}", name);
+
+    tauri::async_runtime::spawn_blocking(move || app.state::<McpSupervisor>().start(&app, &name))
+        .await
+        .map_err(|e| format!("启动任务异常结束: {}", e))?
+}
+
+// 停止 MCP 服务器
+#[tauri::command]
+pub async fn stop_mcp_server(app: AppHandle, name: String) -> Result<(), String> {
+    info!("停止 MCP 服务器: {}", name);
+
+    tauri::async_runtime::spawn_blocking(move || app.state::<McpSupervisor>().stop(&name))
+        .await
+        .map_err(|e| format!("停止任务异常结束: {}", e))?
+}
+
+// 列出应用启动的服务器
+#[tauri::command]
+pub fn list_running_mcp_servers(supervisor: State<'_, McpSupervisor>) -> Vec<RunningServerStatus> {
+    supervisor.list()
+}
@@ -0,0 +1,114 @@
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::mcp_client::McpClient;
+use crate::mcp_supervisor::{self, McpSupervisor};
+use crate::sqlite_db::Database;
+
+// 前端监听的工具列表变更事件
+pub const TOOLS_CHANGED_EVENT: &str = "mcp-tools-changed";
+
+// 缓存的服务器工具列表，启动时无需等待服务器即可展示
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedTools {
+    pub server: String,
+    // tools/list 返回的工具，包含 name、description 和 inputSchema
+    pub tools: Vec<Value>,
+    // 更新时间，Unix 秒
+    pub updated_at: u64,
+}
+
+fn get_db() -> Result<Database, String> {
+    let db = Database::new().map_err(|e| format!("初始化数据库失败: {}", e))?;
+    db.init_tool_cache_table().map_err(|e| format!("初始化表失败: {}", e))?;
+    Ok(db)
+}
+
+// 写入工具列表缓存
+pub fn save_tools(server: &str, tools: &[Value]) -> Result<CachedTools, String> {
+    let cached = CachedTools {
+        server: server.to_string(),
+        tools: tools.to_vec(),
+        updated_at: mcp_supervisor::unix_now(),
+    };
+    let tools_json = serde_json::to_string(tools).map_err(|e| format!("序列化工具列表失败: {}", e))?;
+    get_db()?.get_connection().execute(
+        "INSERT OR REPLACE INTO tool_cache (server, tools, updated_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![server, tools_json, cached.updated_at as i64],
+    ).map_err(|e| format!("保存工具列表失败: {}", e))?;
+    Ok(cached)
+}
+
+// 读取缓存的工具列表，server 为空时返回全部服务器
+pub fn cached_tools(server: Option<&str>) -> Result<Vec<CachedTools>, String> {
+    let db = get_db()?;
+    let conn = db.get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT server, tools, updated_at FROM tool_cache WHERE ?1 IS NULL OR server = ?1 ORDER BY server"
+    ).map_err(|e| format!("准备查询语句失败: {}", e))?;
+    let cached = stmt.query_map([server], |row| {
+        let tools: String = row.get(1)?;
+        let updated_at: i64 = row.get(2)?;
+        Ok(CachedTools {
+            server: row.get(0)?,
+            tools: serde_json::from_str(&tools).unwrap_or_default(),
+            updated_at: updated_at as u64,
+        })
+    })
+    .map_err(|e| format!("查询工具列表失败: {}", e))?
+    .filter_map(|cached| cached.ok())
+    .collect();
+    Ok(cached)
+}
+
+pub fn remove_cached_tools(server: &str) -> Result<(), String> {
+    get_db()?.get_connection()
+        .execute("DELETE FROM tool_cache WHERE server = ?1", [server])
+        .map_err(|e| format!("删除工具列表缓存失败: {}", e))?;
+    Ok(())
+}
+
+// 从服务器重新获取工具列表并写入缓存
+pub fn refresh(app: &AppHandle, server: &str, client: &McpClient) -> Result<CachedTools, String> {
+    let tools = client.list_tools()?;
+    let cached = save_tools(server, &tools)?;
+    info!("服务器 {} 的工具列表已更新: {} 个工具", server, cached.tools.len());
+    if let Err(e) = app.emit(TOOLS_CHANGED_EVENT, json!({ "server": server, "tools": cached.tools })) {
+        warn!("发送 {} 事件失败: {}", TOOLS_CHANGED_EVENT, e);
+    }
+    Ok(cached)
+}
+
+// 服务器启动或发送 tools/list_changed 后在后台刷新缓存
+pub fn refresh_in_background(app: &AppHandle, server: &str) {
+    let app = app.clone();
+    let server = server.to_string();
+    std::thread::spawn(move || {
+        let Some(client) = app.state::<McpSupervisor>().running_client(&server) else {
+            return;
+        };
+        if let Err(e) = refresh(&app, &server, &client) {
+            warn!("刷新服务器 {} 的工具列表失败: {}", server, e);
+        }
+    });
+}
+
+// 获取缓存的工具列表
+#[tauri::command]
+pub fn get_cached_mcp_tools(server: Option<String>) -> Result<Vec<CachedTools>, String> {
+    cached_tools(server.as_deref())
+}
+
+// 立即从服务器刷新工具列表，服务器没有运行时会先启动
+#[tauri::command]
+pub async fn refresh_mcp_tools(app: AppHandle, server: String) -> Result<CachedTools, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let client = app.state::<McpSupervisor>().client(&app, &server)?;
+        refresh(&app, &server, &client)
+    })
+    .await
+    .map_err(|e| format!("刷新工具列表异常结束: {}", e))?
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{info, warn};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::mcp_server_tools;
+use crate::mcp_supervisor::McpSupervisor;
+
+// 前端监听的工具调用进度事件
+pub const TOOL_PROGRESS_EVENT: &str = "mcp-tool-progress";
+// 没有指定时的工具调用超时
+const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(600);
+
+struct ToolCall {
+    server: String,
+    tool: String,
+    cancel: Arc<AtomicBool>,
+}
+
+// 正在进行的工具调用，调用 id 同时作为 progressToken
+#[derive(Default)]
+pub struct ToolCalls {
+    calls: Mutex<HashMap<String, ToolCall>>,
+    next_id: AtomicU64,
+}
+
+impl ToolCalls {
+    fn register(&self, call_id: Option<String>, server: &str, tool: &str) -> Result<(String, Arc<AtomicBool>), String> {
+        let mut calls = self.calls.lock().map_err(|e| format!("获取工具调用失败: {}", e))?;
+        let call_id = call_id.unwrap_or_else(|| format!("call-{}", self.next_id.fetch_add(1, Ordering::SeqCst) + 1));
+        if calls.contains_key(&call_id) {
+            return Err(format!("工具调用 {} 已存在", call_id));
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        calls.insert(call_id.clone(), ToolCall {
+            server: server.to_string(),
+            tool: tool.to_string(),
+            cancel: cancel.clone(),
+        });
+        Ok((call_id, cancel))
+    }
+
+    fn finish(&self, call_id: &str) {
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.remove(call_id);
+        }
+    }
+
+    pub fn cancel(&self, call_id: &str) -> Result<(), String> {
+        let calls = self.calls.lock().map_err(|e| format!("获取工具调用失败: {}", e))?;
+        let call = calls.get(call_id).ok_or_else(|| format!("工具调用不存在: {}", call_id))?;
+        call.cancel.store(true, Ordering::SeqCst);
+        info!("取消工具调用 {}: {} ({})", call_id, call.tool, call.server);
+        Ok(())
+    }
+}
+
+// 把服务器的 notifications/progress 转发给前端，progressToken 即调用 id
+pub fn forward_progress(app: &AppHandle, server: &str, params: Option<Value>) {
+    let params = params.unwrap_or(Value::Null);
+    let payload = json!({
+        "server": server,
+        "callId": params.get("progressToken").cloned().unwrap_or(Value::Null),
+        "progress": params.get("progress").cloned().unwrap_or(Value::Null),
+        "total": params.get("total").cloned().unwrap_or(Value::Null),
+        "message": params.get("message").cloned().unwrap_or(Value::Null),
+    });
+    if let Err(e) = app.emit(TOOL_PROGRESS_EVENT, payload) {
+        warn!("发送工具调用进度事件失败: {}", e);
+    }
+}
+
+// 调用 MCP 工具，服务器没有运行时会先启动
+//
+// call_id 可以由前端指定，便于在调用结束前通过 cancel_tool_call 取消；进度通过 mcp-tool-progress 事件通知
+#[tauri::command]
+pub async fn call_mcp_tool(
+    app: AppHandle,
+    server: String,
+    tool: String,
+    arguments: Option<Value>,
+    call_id: Option<String>,
+    timeout_secs: Option<u64>,
+) -> Result<Value, String> {
+    // 别名解析为原名，禁用的工具不允许调用
+    let tool = mcp_server_tools::resolve_tool(&server, &tool)?;
+    let (call_id, cancel) = app.state::<ToolCalls>().register(call_id, &server, &tool)?;
+    info!("调用工具 {} ({}): {}", tool, server, call_id);
+
+    let timeout = timeout_secs.map(Duration::from_secs).unwrap_or(DEFAULT_TOOL_TIMEOUT);
+    let thread_app = app.clone();
+    let thread_call_id = call_id.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let client = thread_app.state::<McpSupervisor>().client(&thread_app, &server)?;
+        client.call_tool(&tool, arguments.unwrap_or_else(|| json!({})), &thread_call_id, &cancel, timeout)
+    })
+    .await
+    .map_err(|e| format!("工具调用异常结束: {}", e));
+
+    app.state::<ToolCalls>().finish(&call_id);
+    result?
+}
+
+// 取消工具调用：通知服务器并在本地立即结束等待
+#[tauri::command]
+pub fn cancel_tool_call(calls: State<'_, ToolCalls>, call_id: String) -> Result<(), String> {
+    calls.cancel(&call_id)
+}
@@ -1,8 +1,9 @@
+use log::debug;
 use rusqlite::{Result};
 use serde::{Deserialize, Serialize};
 use crate::sqlite_db::Database;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ModelConfig {
     pub provider: String,
     pub api_url: String,
@@ -59,7 +60,7 @@ pub async fn get_model_config(provider: String) -> Result<Option<ModelConfig>, S
 
 #[tauri::command]
 pub async fn save_model_config(provider: String, config: ModelConfig) -> Result<(), String> {
-    println!("保存配置: provider={}, config={:?}", provider, config);
+    debug!("保存配置: provider={}, config={:?}", provider, config);
     
     let db = get_db()?;
     db.init_model_configs_table().map_err(|e| format!("初始化表失败: {}", e))?;
@@ -120,6 +121,30 @@ pub async fn save_model_config(provider: String, config: ModelConfig) -> Result<
     }
 }
 
+// 读取全部模型配置，供后端直接调用模型时使用
+pub fn load_model_configs() -> Result<Vec<ModelConfig>, String> {
+    let db = get_db()?;
+    db.init_model_configs_table().map_err(|e| format!("初始化表失败: {}", e))?;
+
+    let conn = db.get_connection();
+    let mut stmt = conn.prepare("SELECT provider, api_url, model, session_key, endpoint, method FROM model_configs")
+        .map_err(|e| format!("准备查询语句失败: {}", e))?;
+    let configs = stmt.query_map([], |row| {
+        Ok(ModelConfig {
+            provider: row.get(0)?,
+            api_url: row.get(1)?,
+            model: row.get(2)?,
+            session_key: row.get(3)?,
+            endpoint: row.get(4)?,
+            method: row.get(5)?,
+        })
+    })
+    .map_err(|e| format!("查询模型配置失败: {}", e))?
+    .filter_map(|config| config.ok())
+    .collect();
+    Ok(configs)
+}
+
 #[tauri::command]
 pub async fn get_custom_configs(filter_type: Option<String>) -> Result<Vec<ModelConfig>, String> {
     let db = get_db()?;
@@ -0,0 +1,354 @@
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::mcp_installer::{
+    node_entry, node_runtime, path_arg, read_json, run_in_dir, run_with_env, tool_executable,
+    validate_server_name, which_cargo, LaunchCommand,
+};
+use crate::save_mcp_config::{self, get_mcp_servers_root_dir, McpServerConfig};
+
+// 包管理器
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageEcosystem {
+    Npm,
+    Pypi,
+    Cargo,
+}
+
+impl PackageEcosystem {
+    fn key(&self) -> &'static str {
+        match self {
+            PackageEcosystem::Npm => "npm",
+            PackageEcosystem::Pypi => "pypi",
+            PackageEcosystem::Cargo => "cargo",
+        }
+    }
+
+    // 拆分包名和版本号，如 @scope/name@1.0.0、name==1.0.0、name@1.0.0
+    pub fn parse_spec(&self, spec: &str) -> (String, Option<String>) {
+        let split = match self {
+            PackageEcosystem::Pypi if spec.contains("==") => spec.split_once("=="),
+            // npm 的 scope 以 @ 开头，只看第一个字符之后的 @
+            _ => spec.char_indices()
+                .skip(1)
+                .filter(|(_, c)| *c == '@')
+                .last()
+                .map(|(index, _)| (&spec[..index], &spec[index + 1..])),
+        };
+        match split {
+            Some((name, version)) if !version.is_empty() => (name.to_string(), Some(version.to_string())),
+            _ => (spec.to_string(), None),
+        }
+    }
+}
+
+// 从包名推断服务器名称，如 @modelcontextprotocol/server-filesystem -> server-filesystem
+fn server_name_from_package(package: &str) -> Result<String, String> {
+    let name = package.rsplit('/').next().unwrap_or(package).trim_start_matches('@').to_string();
+    validate_server_name(&name)?;
+    Ok(name)
+}
+
+// 在 bin 目录中选择可执行文件，优先与候选名称同名的
+fn pick_binary(bin_dir: &Path, preferred: &[&str]) -> Result<PathBuf, String> {
+    let mut binaries: Vec<PathBuf> = std::fs::read_dir(bin_dir)
+        .map_err(|e| format!("读取 {:?} 失败: {}", bin_dir, e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    binaries.sort();
+
+    let stem = |path: &Path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    preferred.iter()
+        .find_map(|name| binaries.iter().find(|path| stem(path) == *name))
+        .or_else(|| binaries.first())
+        .cloned()
+        .ok_or_else(|| format!("{} 中没有可执行文件", bin_dir.to_string_lossy()))
+}
+
+// 用 bun 把 npm 包以固定版本安装到服务器目录的 node_modules 中
+fn install_npm(dir: &Path, package: &str, version: Option<&str>, bin: Option<&str>) -> Result<(String, LaunchCommand), String> {
+    let bun = tool_executable("bun")?;
+    let spec = match version {
+        Some(version) => format!("{}@{}", package, version),
+        None => package.to_string(),
+    };
+    run_in_dir(&bun, &["add", "--exact", &spec], dir)?;
+
+    let installed_version = read_json(&dir.join("package.json"))?
+        .get("dependencies")
+        .and_then(|dependencies| dependencies.get(package))
+        .and_then(|version| version.as_str())
+        .map(String::from)
+        .ok_or_else(|| format!("package.json 中没有 {}", package))?;
+
+    let package_dir = dir.join("node_modules").join(package);
+    let manifest = read_json(&package_dir.join("package.json"))?;
+    let preferred = bin.or_else(|| package.rsplit('/').next());
+    let entry = node_entry(&package_dir, &manifest, preferred)
+        .filter(|entry| entry.exists())
+        .ok_or_else(|| format!("无法确定 {} 的入口文件", package))?;
+
+    Ok((installed_version, LaunchCommand {
+        command: node_runtime().to_string(),
+        args: vec![path_arg(&entry)],
+        docker: None,
+    }))
+}
+
+// 用 uv tool install 把 PyPI 包安装到服务器目录中，工具环境和可执行文件都不放在全局目录
+fn install_pypi(dir: &Path, package: &str, version: Option<&str>, script: Option<&str>) -> Result<(String, LaunchCommand), String> {
+    let uv = tool_executable("uv")?;
+    let tool_dir = dir.join(".uv-tools");
+    let bin_dir = dir.join("bin");
+    let envs = [("UV_TOOL_DIR", tool_dir.as_path()), ("UV_TOOL_BIN_DIR", bin_dir.as_path())];
+
+    let spec = match version {
+        Some(version) => format!("{}=={}", package, version),
+        None => package.to_string(),
+    };
+    run_with_env(&uv, &["tool", "install", "--force", &spec], dir, &envs)?;
+
+    // uv tool list 的输出形如 "mcp-server-fetch v0.6.2"
+    let normalize = |name: &str| name.to_lowercase().replace('_', "-");
+    let installed_version = run_with_env(&uv, &["tool", "list"], dir, &envs)?
+        .lines()
+        .find_map(|line| {
+            let (name, version) = line.split_once(' ')?;
+            (normalize(name) == normalize(package)).then(|| version.trim().trim_start_matches('v').to_string())
+        })
+        .ok_or_else(|| format!("uv tool list 中没有 {}", package))?;
+
+    let mut preferred = Vec::new();
+    preferred.extend(script);
+    preferred.push(package);
+    let binary = pick_binary(&bin_dir, &preferred)?;
+
+    Ok((installed_version, LaunchCommand {
+        command: path_arg(&binary),
+        args: Vec::new(),
+        docker: None,
+    }))
+}
+
+// 用 cargo install 把 crate 编译安装到服务器目录的 bin 中
+fn install_cargo(dir: &Path, package: &str, version: Option<&str>, bin: Option<&str>) -> Result<(String, LaunchCommand), String> {
+    let cargo = which_cargo()?;
+    let root = path_arg(dir);
+    let mut args = vec!["install", "--locked", "--root", root.as_str(), package];
+    if let Some(version) = version {
+        args.extend(["--version", version]);
+    }
+    run_in_dir(&cargo, &args, dir)?;
+
+    // .crates.toml 的键形如 "name 1.0.0 (registry+https://...)"
+    let crates: toml::Value = std::fs::read_to_string(dir.join(".crates.toml"))
+        .map_err(|e| format!("读取 .crates.toml 失败: {}", e))
+        .and_then(|content| toml::from_str(&content).map_err(|e| format!("解析 .crates.toml 失败: {}", e)))?;
+    let installed_version = crates.get("v1")
+        .and_then(|installed| installed.as_table())
+        .and_then(|installed| installed.keys().find_map(|key| {
+            let mut parts = key.split(' ');
+            (parts.next() == Some(package)).then(|| parts.next().unwrap_or_default().to_string())
+        }))
+        .ok_or_else(|| format!(".crates.toml 中没有 {}", package))?;
+
+    let mut preferred = Vec::new();
+    preferred.extend(bin);
+    preferred.push(package);
+    let binary = pick_binary(&dir.join("bin"), &preferred)?;
+
+    Ok((installed_version, LaunchCommand {
+        command: path_arg(&binary),
+        args: Vec::new(),
+        docker: None,
+    }))
+}
+
+// 把包以固定版本安装到服务器目录，返回实际安装的版本和启动命令
+//
+// version 为空时安装当前最新版本并固定下来；bin 用于包中有多个可执行文件时选择
+pub fn install_package(
+    ecosystem: PackageEcosystem,
+    package: &str,
+    version: Option<&str>,
+    bin: Option<&str>,
+    dir: &Path,
+) -> Result<(String, LaunchCommand), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("创建服务器目录失败: {}", e))?;
+    info!("安装 {} 包 {} {}", ecosystem.key(), package, version.unwrap_or("(最新版本)"));
+
+    match ecosystem {
+        PackageEcosystem::Npm => install_npm(dir, package, version, bin),
+        PackageEcosystem::Pypi => install_pypi(dir, package, version, bin),
+        PackageEcosystem::Cargo => install_cargo(dir, package, version, bin),
+    }
+}
+
+// 通过包管理器安装 MCP 服务器，启动命令指向服务器目录中的本地副本
+pub fn install_from_package(
+    ecosystem: PackageEcosystem,
+    package: &str,
+    version: Option<&str>,
+    name: Option<&str>,
+    args: Vec<String>,
+    env: Option<Value>,
+) -> Result<McpServerConfig, String> {
+    let (package, spec_version) = ecosystem.parse_spec(package);
+    let version = version.map(String::from).or(spec_version);
+    let name = match name {
+        Some(name) => {
+            validate_server_name(name)?;
+            name.to_string()
+        },
+        None => server_name_from_package(&package)?,
+    };
+
+    if save_mcp_config::count_mcp_server_config(&name).unwrap_or(0) > 0 {
+        return Err(format!("服务器 {} 已存在", name));
+    }
+    let install_dir = get_mcp_servers_root_dir()?.join(&name);
+    let created = !install_dir.exists();
+
+    let (installed_version, launch) = match install_package(ecosystem, &package, version.as_deref(), None, &install_dir) {
+        Ok(result) => result,
+        Err(e) => {
+            if created && install_dir.exists() {
+                if let Err(remove_err) = std::fs::remove_dir_all(&install_dir) {
+                    warn!("删除 {:?} 失败: {}", install_dir, remove_err);
+                }
+            }
+            return Err(e);
+        },
+    };
+
+    let mut config = McpServerConfig {
+        name: name.clone(),
+        description: Some(format!("{} {}", package, installed_version)),
+        command: launch.command,
+        args: launch.args.into_iter().chain(args).collect(),
+        is_active: true,
+        env,
+        type_: Some("stdio".to_string()),
+        base_url: None,
+        install_dir: Some(path_arg(&install_dir)),
+        installed_commit: None,
+        upgraded_at: None,
+        package: Some(format!("{}:{}@{}", ecosystem.key(), package, installed_version)),
+        docker: None,
+        lifecycle: None,
+        runtime_status: None,
+    };
+    save_mcp_config::save_mcp_server_config(config.clone())?;
+
+    info!("{} 已安装: {} {:?}", name, config.command, config.args);
+    config.runtime_status = save_mcp_config::check_server_runtime(&config);
+    Ok(config)
+}
+
+// 从 npx / bunx / uvx 启动参数中解析出的包
+struct RemotePackage {
+    ecosystem: PackageEcosystem,
+    spec: String,
+    // 包中要运行的可执行文件
+    bin: Option<String>,
+    // 传给服务器的其余参数
+    server_args: Vec<String>,
+}
+
+// 解析 npx -y pkg ...、bunx pkg ...、uvx [--from pkg] cmd ... 形式的启动命令
+fn parse_remote_command(command: &str, args: &[String]) -> Option<RemotePackage> {
+    let program = Path::new(command).file_stem()?.to_string_lossy().to_string();
+    let ecosystem = match program.as_str() {
+        "npx" | "bunx" => PackageEcosystem::Npm,
+        "uvx" => PackageEcosystem::Pypi,
+        _ => return None,
+    };
+    // 需要跳过参数值的选项
+    let (package_flags, value_flags): (&[&str], &[&str]) = match ecosystem {
+        PackageEcosystem::Npm => (&["-p", "--package"], &[]),
+        _ => (&["--from"], &["--python", "-p", "--with", "--index-url", "--index"]),
+    };
+
+    let mut from: Option<String> = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if package_flags.contains(&arg.as_str()) {
+            from = iter.next().cloned();
+        } else if value_flags.contains(&arg.as_str()) {
+            iter.next();
+        } else if arg.starts_with('-') {
+            continue;
+        } else {
+            let server_args = iter.cloned().collect();
+            return Some(match from {
+                Some(spec) => RemotePackage { ecosystem, spec, bin: Some(arg.clone()), server_args },
+                None => RemotePackage { ecosystem, spec: arg.clone(), bin: None, server_args },
+            });
+        }
+    }
+    None
+}
+
+// 把使用 npx -y 或 uvx 启动的服务器改为固定版本的本地安装，避免每次启动都下载最新版本
+pub fn pin_server(name: &str) -> Result<McpServerConfig, String> {
+    let config = save_mcp_config::get_mcp_server(name)?;
+    let remote = parse_remote_command(&config.command, &config.args)
+        .ok_or_else(|| format!("服务器 {} 不是通过 npx、bunx 或 uvx 启动的", name))?;
+    let (package, version) = remote.ecosystem.parse_spec(&remote.spec);
+
+    let install_dir = match &config.install_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => get_mcp_servers_root_dir()?.join(name),
+    };
+    let (installed_version, launch) = install_package(
+        remote.ecosystem,
+        &package,
+        version.as_deref(),
+        remote.bin.as_deref(),
+        &install_dir,
+    )?;
+
+    let mut pinned = McpServerConfig {
+        command: launch.command,
+        args: launch.args.into_iter().chain(remote.server_args).collect(),
+        docker: None,
+        install_dir: Some(path_arg(&install_dir)),
+        package: Some(format!("{}:{}@{}", remote.ecosystem.key(), package, installed_version)),
+        ..config
+    };
+    save_mcp_config::save_mcp_server_config(pinned.clone())?;
+
+    info!("{} 已固定为 {} {}", name, package, installed_version);
+    pinned.runtime_status = save_mcp_config::check_server_runtime(&pinned);
+    Ok(pinned)
+}
+
+// 通过 npm、PyPI 或 crates.io 安装 MCP 服务器
+#[tauri::command]
+pub async fn install_mcp_server_from_package(
+    ecosystem: PackageEcosystem,
+    package: String,
+    version: Option<String>,
+    name: Option<String>,
+    args: Option<Vec<String>>,
+    env: Option<Value>,
+) -> Result<McpServerConfig, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        install_from_package(ecosystem, &package, version.as_deref(), name.as_deref(), args.unwrap_or_default(), env)
+    })
+    .await
+    .map_err(|e| format!("安装任务异常结束: {}", e))?
+}
+
+// 将 npx / uvx 启动的服务器固定到本地安装的版本
+#[tauri::command]
+pub async fn pin_mcp_server_package(name: String) -> Result<McpServerConfig, String> {
+    tauri::async_runtime::spawn_blocking(move || pin_server(&name))
+        .await
+        .map_err(|e| format!("安装任务异常结束: {}", e))?
+}
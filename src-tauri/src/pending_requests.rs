@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
+// 等待用户在前端处理的请求，如服务器的采样和信息收集请求
+//
+// 请求方登记后通过事件通知前端并阻塞等待，前端通过命令回复
+pub struct PendingRequests<T> {
+    pending: Mutex<HashMap<String, mpsc::Sender<T>>>,
+    next_id: AtomicU64,
+}
+
+impl<T> Default for PendingRequests<T> {
+    fn default() -> Self {
+        PendingRequests {
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<T> PendingRequests<T> {
+    // 登记请求，返回形如 <prefix>-<n> 的 id
+    pub fn register(&self, prefix: &str) -> Result<(String, mpsc::Receiver<T>), String> {
+        let id = format!("{}-{}", prefix, self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+        let (sender, receiver) = mpsc::channel();
+        self.pending.lock()
+            .map_err(|e| format!("获取等待中的请求失败: {}", e))?
+            .insert(id.clone(), sender);
+        Ok((id, receiver))
+    }
+
+    // 等待前端回复，超时后移除请求
+    pub fn wait(&self, id: &str, receiver: mpsc::Receiver<T>, timeout: Duration) -> Option<T> {
+        let reply = receiver.recv_timeout(timeout).ok();
+        if reply.is_none() {
+            self.remove(id);
+        }
+        reply
+    }
+
+    pub fn remove(&self, id: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(id);
+        }
+    }
+
+    pub fn respond(&self, id: &str, reply: T) -> Result<(), String> {
+        let sender = self.pending.lock()
+            .map_err(|e| format!("获取等待中的请求失败: {}", e))?
+            .remove(id)
+            .ok_or_else(|| format!("请求不存在或已超时: {}", id))?;
+        sender.send(reply).map_err(|_| format!("请求已结束: {}", id))
+    }
+}
@@ -1,11 +1,15 @@
 use std::path;
 
+use log::debug;
 use rusqlite::{Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use crate::docker_runtime::DockerConfig;
+use crate::mcp_lifecycle::{self, Lifecycle};
 use crate::sqlite_db::Database;
+use crate::tools::{self, RuntimeStatus};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct McpServerConfig {
     #[serde(rename = "name")]
     pub name: String,
@@ -19,13 +23,63 @@ pub struct McpServerConfig {
     pub type_: Option<String>,
     #[serde(rename = "baseUrl")]
     pub base_url: Option<String>,
+    // 从 git 等来源安装的服务器所在目录
+    #[serde(rename = "installDir", default)]
+    pub install_dir: Option<String>,
+    // git 安装的服务器当前检出的提交
+    #[serde(rename = "installedCommit", default)]
+    pub installed_commit: Option<String>,
+    // 最近一次升级的时间（UTC）
+    #[serde(rename = "upgradedAt", default)]
+    pub upgraded_at: Option<String>,
+    // 通过包管理器安装的包及其固定版本，如 npm:@modelcontextprotocol/server-filesystem@2025.1.14
+    #[serde(default)]
+    pub package: Option<String>,
+    // docker 类型服务器的容器设置
+    #[serde(default)]
+    pub docker: Option<DockerConfig>,
+    // 生命周期，未设置时按需启动
+    #[serde(default)]
+    pub lifecycle: Option<Lifecycle>,
+    // 启动命令所需运行时的状态，仅在返回给前端时填充
+    #[serde(rename = "runtimeStatus", default)]
+    pub runtime_status: Option<RuntimeStatus>,
+}
+
+// 检查服务器启动命令所需的运行时，SSE 服务器没有本地命令
+pub fn check_server_runtime(config: &McpServerConfig) -> Option<RuntimeStatus> {
+    if config.docker.is_some() {
+        return Some(tools::check_runtime("docker"));
+    }
+    if config.type_.as_deref() == Some("sse") || config.command.is_empty() {
+        return None;
+    }
+    Some(tools::check_runtime(&config.command))
+}
+
+// 将容器设置序列化为 JSON，非 docker 服务器为空字符串
+fn docker_to_json(docker: Option<&DockerConfig>) -> Result<String, String> {
+    match docker {
+        Some(docker) => serde_json::to_string(docker).map_err(|e| format!("序列化docker失败: {}", e)),
+        None => Ok(String::new()),
+    }
 }
 
 fn get_db() -> Result<Database, String> {
     Database::new().map_err(|e| format!("初始化数据库失败: {}", e))
 }
 
+// 将生命周期序列化为 JSON，未设置时为空字符串
+fn lifecycle_to_json(lifecycle: Option<&Lifecycle>) -> Result<String, String> {
+    match lifecycle {
+        Some(lifecycle) => serde_json::to_string(lifecycle).map_err(|e| format!("序列化lifecycle失败: {}", e)),
+        None => Ok(String::new()),
+    }
+}
+
 const TABLE_NAME: &str = "mcpServers";
+// 查询服务器配置时的列，顺序与 map_server_row 一致
+const SERVER_COLUMNS: &str = "name, command, args, is_active, env, description, type, base_url, install_dir, installed_commit, upgraded_at, package, docker, lifecycle";
 
 pub fn save_mcp_server_config(config: McpServerConfig) -> Result<(), String> {
     println!("保存配置: config={:?}", config);
@@ -41,7 +95,7 @@ pub fn save_mcp_server_config(config: McpServerConfig) -> Result<(), String> {
     
     // 定义 insert_sql 变量
     let insert_sql = format!(
-        "INSERT OR REPLACE INTO {} (name, command, args, is_active, env, description, type, base_url) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT OR REPLACE INTO {} (name, command, args, is_active, env, description, type, base_url, install_dir, installed_commit, upgraded_at, package, docker, lifecycle) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
         TABLE_NAME
     );
     
@@ -57,6 +111,12 @@ pub fn save_mcp_server_config(config: McpServerConfig) -> Result<(), String> {
     let description = config.description.unwrap_or_default();
     let type_ = config.type_.unwrap_or_default();
     let base_url = config.base_url.unwrap_or_default();  // 修改为 snake_case
+    let install_dir = config.install_dir.unwrap_or_default();
+    let installed_commit = config.installed_commit.unwrap_or_default();
+    let upgraded_at = config.upgraded_at.unwrap_or_default();
+    let package = config.package.unwrap_or_default();
+    let docker_json = docker_to_json(config.docker.as_ref())?;
+    let lifecycle_json = lifecycle_to_json(config.lifecycle.as_ref())?;
     
     let result = conn.execute(
         &insert_sql,
@@ -69,6 +129,12 @@ pub fn save_mcp_server_config(config: McpServerConfig) -> Result<(), String> {
             &description,
             &type_,
             &base_url,
+            &install_dir,
+            &installed_commit,
+            &upgraded_at,
+            &package,
+            &docker_json,
+            &lifecycle_json,
         ],
     );
 
@@ -86,27 +152,196 @@ pub fn save_mcp_server_config(config: McpServerConfig) -> Result<(), String> {
 }
 
 
+// 将查询结果的一行转换为服务器配置
+fn map_server_row(row: &rusqlite::Row) -> rusqlite::Result<McpServerConfig> {
+    let name: String = row.get(0)?;
+    let command: String = row.get(1)?;
+    let args_json: String = row.get(2)?;
+    let is_active: String = row.get(3)?;
+    let env_json: String = row.get(4)?;
+    let description: Option<String> = row.get(5).ok();
+    let type_: Option<String> = row.get(6).ok();
+    let base_url: Option<String> = row.get(7).ok();
+    // 未设置的可选列可能是 NULL 或空字符串
+    let optional = |index: usize| -> Option<String> {
+        row.get::<_, Option<String>>(index).ok().flatten().filter(|value| !value.is_empty())
+    };
+
+    let args: Vec<String> = serde_json::from_str(&args_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
+            0,
+            rusqlite::types::Type::Text,
+            Box::new(e),
+        ))?;
+
+    let env: Option<Value> = if env_json.is_empty() {
+        None
+    } else {
+        Some(serde_json::from_str(&env_json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
+                0,
+                rusqlite::types::Type::Text,
+                Box::new(e),
+            ))?)
+    };
+
+    Ok(McpServerConfig {
+        name,
+        command,
+        args,
+        is_active: is_active.parse().unwrap_or(true),
+        env,
+        description,
+        type_,
+        base_url,
+        install_dir: optional(8),
+        installed_commit: optional(9),
+        upgraded_at: optional(10),
+        package: optional(11),
+        docker: optional(12).and_then(|docker| serde_json::from_str(&docker).ok()),
+        lifecycle: optional(13).and_then(|lifecycle| serde_json::from_str(&lifecycle).ok()),
+        runtime_status: None,
+    })
+}
+
+// 按名称获取服务器配置
+pub fn get_mcp_server(name: &str) -> Result<McpServerConfig, String> {
+    let db = get_db()?;
+    db.init_mcp_servers_table().map_err(|e| format!("初始化表失败: {}", e))?;
+
+    let conn = db.get_connection();
+    conn.query_row(
+        &format!("SELECT {} FROM {} WHERE name = ?1", SERVER_COLUMNS, TABLE_NAME),
+        [name],
+        map_server_row,
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("服务器不存在: {}", name),
+        e => format!("查询服务器配置失败: {}", e),
+    })
+}
+
+// 升级后更新启动命令和检出的提交，并记录升级时间
+pub fn record_server_upgrade(name: &str, command: &str, args: &[String], installed_commit: &str) -> Result<(), String> {
+    let db = get_db()?;
+    let conn = db.get_connection();
+
+    let args_json = serde_json::to_string(args)
+        .map_err(|e| format!("序列化args失败: {}", e))?;
+    conn.execute(
+        &format!(
+            "UPDATE {} SET command = ?1, args = ?2, installed_commit = ?3, upgraded_at = datetime('now') WHERE name = ?4",
+            TABLE_NAME
+        ),
+        [command, &args_json, installed_commit, name],
+    ).map_err(|e| format!("更新服务器升级信息失败: {}", e))?;
+
+    println!("已记录服务器 {} 升级到 {}", name, installed_commit);
+    Ok(())
+}
+
+// 修改服务器的生命周期
+pub fn update_server_lifecycle(name: &str, lifecycle: &Lifecycle) -> Result<(), String> {
+    let db = get_db()?;
+    db.init_mcp_servers_table().map_err(|e| format!("初始化表失败: {}", e))?;
+    let lifecycle_json = lifecycle_to_json(Some(lifecycle))?;
+    let updated = db.get_connection().execute(
+        &format!("UPDATE {} SET lifecycle = ?1 WHERE name = ?2", TABLE_NAME),
+        [&lifecycle_json, name],
+    ).map_err(|e| format!("更新生命周期失败: {}", e))?;
+    if updated == 0 {
+        return Err(format!("服务器 {} 不存在", name));
+    }
+    Ok(())
+}
+
+// 启用或停用服务器
+pub fn set_server_active(name: &str, active: bool) -> Result<(), String> {
+    let db = get_db()?;
+    db.init_mcp_servers_table().map_err(|e| format!("初始化表失败: {}", e))?;
+    let updated = db.get_connection().execute(
+        &format!("UPDATE {} SET is_active = ?1 WHERE name = ?2", TABLE_NAME),
+        [&active.to_string(), name],
+    ).map_err(|e| format!("更新服务器状态失败: {}", e))?;
+    if updated == 0 {
+        return Err(format!("服务器 {} 不存在", name));
+    }
+    Ok(())
+}
+
+// 记录服务器进程和容器，停止后传入 None 清除
+pub fn update_server_process(name: &str, pid: Option<u32>, container_id: Option<&str>) -> Result<(), String> {
+    let db = get_db()?;
+    db.init_mcp_servers_table().map_err(|e| format!("初始化表失败: {}", e))?;
+
+    db.get_connection().execute(
+        &format!("UPDATE {} SET pid = ?1, container_id = ?2 WHERE name = ?3", TABLE_NAME),
+        rusqlite::params![pid, container_id, name],
+    ).map_err(|e| format!("更新服务器进程失败: {}", e))?;
+    Ok(())
+}
+
+// 读取服务器记录的进程和容器
+pub fn recorded_process(name: &str) -> Result<(Option<u32>, Option<String>), String> {
+    let db = get_db()?;
+    db.init_mcp_servers_table().map_err(|e| format!("初始化表失败: {}", e))?;
+    db.get_connection().query_row(
+        &format!("SELECT pid, container_id FROM {} WHERE name = ?1", TABLE_NAME),
+        [name],
+        |row| Ok((row.get(0)?, row.get::<_, Option<String>>(1)?.filter(|id| !id.is_empty()))),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("服务器不存在: {}", name),
+        e => format!("查询服务器进程失败: {}", e),
+    })
+}
+
+// 清除上次运行记录的进程，返回记录的容器 id，用于启动时清理遗留容器
+pub fn take_recorded_processes() -> Result<Vec<String>, String> {
+    let db = get_db()?;
+    db.init_mcp_servers_table().map_err(|e| format!("初始化表失败: {}", e))?;
+
+    let conn = db.get_connection();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT container_id FROM {} WHERE container_id IS NOT NULL AND container_id != ''",
+        TABLE_NAME
+    )).map_err(|e| format!("准备查询语句失败: {}", e))?;
+    let container_ids = stmt.query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("查询容器失败: {}", e))?
+        .filter_map(|id| id.ok())
+        .collect();
+
+    conn.execute(&format!("UPDATE {} SET pid = NULL, container_id = NULL", TABLE_NAME), [])
+        .map_err(|e| format!("清除服务器进程失败: {}", e))?;
+    Ok(container_ids)
+}
+
 #[tauri::command]
 pub async fn get_all_mcp_servers(is_active: Option<bool>) -> Result<Vec<McpServerConfig>, String> {
+    load_mcp_servers(is_active)
+}
+
+// 读取服务器配置，is_active 为空时返回全部服务器
+pub fn load_mcp_servers(is_active: Option<bool>) -> Result<Vec<McpServerConfig>, String> {
     let db = get_db()?;
     db.init_mcp_servers_table().map_err(|e| format!("初始化表失败: {}", e))?;
     
     let conn = db.get_connection();
     
     // 根据 is_active 参数构建 SQL 查询
-    println!("is_active: {:?}", is_active);  // Use the debug formatter {:?} for Option types
+    debug!("is_active: {:?}", is_active);  // Use the debug formatter {:?} for Option types
     let sql = match is_active {
         Some(active) => format!(
-            "SELECT name, command, args, is_active, env, description, type, base_url FROM {} WHERE is_active = '{}'",
+            "SELECT {} FROM {} WHERE is_active = '{}'",
+            SERVER_COLUMNS,
             TABLE_NAME,
             active.to_string() // 不再取反
         ),
         None => format!(
-            "SELECT name, command, args, is_active, env, description, type, base_url FROM {}",
+            "SELECT {} FROM {}",
+            SERVER_COLUMNS,
             TABLE_NAME
         ),
     };
-    println!("{}", sql);
+    debug!("{}", sql);
     let mut stmt = match conn.prepare(&sql) {
         Ok(stmt) => stmt,
         Err(e) => {
@@ -116,52 +351,12 @@ pub async fn get_all_mcp_servers(is_active: Option<bool>) -> Result<Vec<McpServe
         }
     };
     
-    // Define the row mapping function outside the if-else block
-    let map_row = |row: &rusqlite::Row| -> rusqlite::Result<McpServerConfig> {
-        let name: String = row.get(0)?;
-        let command: String = row.get(1)?;
-        let args_json: String = row.get(2)?;
-        let is_active: String = row.get(3)?;
-        let env_json: String = row.get(4)?;
-        let description: Option<String> = row.get(5).ok();
-        let type_: Option<String> = row.get(6).ok();
-        let base_url: Option<String> = row.get(7).ok();
-        
-        let args: Vec<String> = serde_json::from_str(&args_json)
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                0,
-                rusqlite::types::Type::Text,
-                Box::new(e),
-            ))?;
-        
-        let env: Option<Value> = if env_json.is_empty() {
-            None
-        } else {
-            Some(serde_json::from_str(&env_json)
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                    0,
-                    rusqlite::types::Type::Text,
-                    Box::new(e),
-                ))?)
-        };
-        
-        Ok(McpServerConfig {
-            name,
-            command,
-            args,
-            is_active: is_active.parse().unwrap_or(true),
-            env,
-            description,
-            type_,
-            base_url,
-        })
-    };
-    
-    let rows = stmt.query_map([], map_row).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], map_server_row).map_err(|e| e.to_string())?;
     
     let mut configs = Vec::new();
     for row in rows {
-        if let Ok(config) = row {
+        if let Ok(mut config) = row {
+            config.runtime_status = check_server_runtime(&config);
             configs.push(config);
         }
     }
@@ -185,6 +380,7 @@ pub fn parse_mcp_config(config: &str) -> Result<String, String> {
             let mut env_content = String::new();
             let mut success_count = 0;
             let mut error_messages = Vec::new();
+            let mut runtime_messages = Vec::new();
             
             // 遍历所有服务器配置
             if let Some(servers) = mcp_servers.as_object() {
@@ -215,8 +411,32 @@ pub fn parse_mcp_config(config: &str) -> Result<String, String> {
                         }
                     }
                     
+                    // docker 类型的服务器没有 command，容器设置与其他字段写在同一层
+                    let docker = if server_config.get("type").and_then(|v| v.as_str()) == Some("docker") {
+                        match serde_json::from_value::<DockerConfig>(server_config.clone()) {
+                            Ok(docker) => Some(docker),
+                            Err(e) => {
+                                error_messages.push(format!("服务器 {} 的容器设置无效: {}", server_name, e));
+                                None
+                            }
+                        }
+                    } else {
+                        None
+                    };
+                    let lifecycle = match server_config.get("lifecycle").map(mcp_lifecycle::parse_lifecycle) {
+                        Some(Ok(lifecycle)) => Some(lifecycle),
+                        Some(Err(e)) => {
+                            error_messages.push(format!("服务器 {} 的{}", server_name, e));
+                            None
+                        },
+                        None => None,
+                    };
+                    let command = server_config.get("command")
+                        .and_then(|v| v.as_str())
+                        .or(docker.as_ref().map(|_| "docker"));
+
                     // 提取服务器配置并保存到数据库
-                    if let Some(command) = server_config.get("command").and_then(|v| v.as_str()) {
+                    if let Some(command) = command {
                         let args = server_config.get("args")
                             .and_then(|v| v.as_array())
                             .map(|arr| arr.iter()
@@ -238,8 +458,26 @@ pub fn parse_mcp_config(config: &str) -> Result<String, String> {
                             description: server_config.get("description").and_then(|v| v.as_str()).map(String::from),
                             type_: server_config.get("type").and_then(|v| v.as_str()).map(String::from),
                             base_url: server_config.get("baseUrl").and_then(|v| v.as_str()).map(String::from),
+                            install_dir: None,
+                            installed_commit: None,
+                            upgraded_at: None,
+                            package: None,
+                            docker,
+                            lifecycle,
+                            runtime_status: None,
                         };
 
+                        // 检查启动命令所需的运行时是否已安装
+                        match check_server_runtime(&config) {
+                            Some(RuntimeStatus::MissingRuntime { tool, command }) => {
+                                runtime_messages.push(format!("{}: 命令 {} 需要安装 {}", server_name, command, tool));
+                            },
+                            Some(RuntimeStatus::Unavailable { command }) => {
+                                runtime_messages.push(format!("{}: 未找到命令 {}", server_name, command));
+                            },
+                            _ => {}
+                        }
+
                         let result = if exists {
                             update_mcp_server_config(config)
                         } else {
@@ -281,6 +519,13 @@ pub fn parse_mcp_config(config: &str) -> Result<String, String> {
             let mut result_message = format!("环境变量已成功写入\n");
             result_message.push_str(&format!("成功保存 {} 个服务器配置\n", success_count));
             
+            if !runtime_messages.is_empty() {
+                result_message.push_str("以下服务器缺少运行时：\n");
+                for message in runtime_messages {
+                    result_message.push_str(&format!("- {}\n", message));
+                }
+            }
+            
             if !error_messages.is_empty() {
                 result_message.push_str("发生以下错误：\n");
                 for error in error_messages {
@@ -313,8 +558,11 @@ pub fn update_mcp_server_config(config: McpServerConfig) -> Result<(), String> {
         "{}".to_string()
     };
     
+    let docker_json = docker_to_json(config.docker.as_ref())?;
+    let lifecycle_json = lifecycle_to_json(config.lifecycle.as_ref())?;
+
     let update_sql = format!(
-        "UPDATE {} SET command = ?1, args = ?2, is_active = ?3, env = ?4 WHERE name = ?5",
+        "UPDATE {} SET command = ?1, args = ?2, is_active = ?3, env = ?4, docker = ?5, lifecycle = ?6 WHERE name = ?7",
         TABLE_NAME
     );
     
@@ -325,8 +573,10 @@ pub fn update_mcp_server_config(config: McpServerConfig) -> Result<(), String> {
         [
             &config.command,
             &args_json,
-            &(!config.is_active).to_string(), // Note: inverted logic
+            &config.is_active.to_string(),
             &env_json,
+            &docker_json,
+            &lifecycle_json,
             &config.name,
         ],
     );
@@ -344,7 +594,6 @@ pub fn update_mcp_server_config(config: McpServerConfig) -> Result<(), String> {
     }
 }
 
-#[allow(dead_code)]
 pub fn count_mcp_server_config(server_name: &str) -> Result<i32, String> {
     let db = get_db()?;
     let conn = db.get_connection();
@@ -371,6 +620,9 @@ pub fn delete_mcp_server_config(server_name: String) -> Result<(), String> {
         [&server_name],
     ) {
         Ok(_) => {
+            if let Err(e) = crate::mcp_tool_cache::remove_cached_tools(&server_name) {
+                println!("{}", e);
+            }
             println!("删除服务器配置成功: {}", server_name);
             Ok(())
         },
@@ -391,9 +643,14 @@ fn get_user_home_dir() -> Result<path::PathBuf, String> {
     }
 }
 
-fn get_mcp_server_dir(server_name: &str) -> Result<path::PathBuf, String> {
+// MCP 服务器目录的根目录 (~/.omni-mcp/mcpServer)
+pub fn get_mcp_servers_root_dir() -> Result<path::PathBuf, String> {
     let home_dir = get_user_home_dir()?;
-    let server_dir = home_dir.join(".omni-mcp").join("mcpServer").join(server_name);
+    Ok(home_dir.join(".omni-mcp").join("mcpServer"))
+}
+
+fn get_mcp_server_dir(server_name: &str) -> Result<path::PathBuf, String> {
+    let server_dir = get_mcp_servers_root_dir()?.join(server_name);
     Ok(server_dir)
 }
 // 创建MCP服务器目录
@@ -404,4 +661,29 @@ pub fn create_mcp_server_dir(server_name: &str) -> Result<path::PathBuf, String>
         .map_err(|e| format!("创建服务器目录失败: {}", e))?;
     
     Ok(server_dir)
-}
\ No newline at end of file
+}
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_env::TestEnv;
+
+    #[test]
+    fn reimported_server_stays_active() {
+        let mut env = TestEnv::lock();
+        let root = std::env::temp_dir().join(format!("omni-mcp-config-test-{}", std::process::id()));
+        std::fs::remove_dir_all(&root).ok();
+        env.isolate_home(&root);
+
+        let config = r#"{"mcpServers":{"reimported":{"command":"node","args":["index.js"]}}}"#;
+        parse_mcp_config(config).unwrap();
+        assert!(get_mcp_server("reimported").unwrap().is_active);
+
+        // 再次导入同名服务器会走更新路径
+        parse_mcp_config(config).unwrap();
+        assert!(get_mcp_server("reimported").unwrap().is_active);
+        let active = load_mcp_servers(Some(true)).unwrap();
+        assert!(active.iter().any(|server| server.name == "reimported"));
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+
+use log::debug;
 use rusqlite::{Connection, Result};
 use dirs;
 
@@ -5,15 +8,19 @@ pub struct Database {
     conn: Connection,
 }
 
+// 应用数据目录，数据库文件保存在这里
+pub fn app_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("omni-mcp-app"))
+}
+
 impl Database {
     pub fn new() -> Result<Self> {
-        let app_dir = dirs::data_dir()
-            .expect("无法获取应用数据目录")
-            .join("omni-mcp-app");
+        let app_dir = app_data_dir().expect("无法获取应用数据目录");
         
         std::fs::create_dir_all(&app_dir).expect("无法创建应用数据目录");
         let db_path = app_dir.join("omni_mcp.db");
-        println!("数据库路径: {:?}", db_path);
+        // stdio 网关辅助程序的标准输出是协议通道，不能输出日志
+        debug!("数据库路径: {:?}", db_path);
         
         let conn = Connection::open(db_path)?;
         Ok(Database { conn })
@@ -114,7 +121,13 @@ impl Database {
                     is_active BOOLEAN,
                     pid INTEGER,
                     install_dir TEXT,
-                    env TEXT
+                    env TEXT,
+                    installed_commit TEXT,
+                    upgraded_at TEXT,
+                    package TEXT,
+                    docker TEXT,
+                    container_id TEXT,
+                    lifecycle TEXT
                 )"
             )?;
         } else {
@@ -122,7 +135,13 @@ impl Database {
             let columns = [
                 ("install_dir", "TEXT"),
                 ("pid", "INTEGER"),
-                ("env", "TEXT")
+                ("env", "TEXT"),
+                ("installed_commit", "TEXT"),
+                ("upgraded_at", "TEXT"),
+                ("package", "TEXT"),
+                ("docker", "TEXT"),
+                ("container_id", "TEXT"),
+                ("lifecycle", "TEXT")
             ];
             
             for (column_name, column_type) in columns.iter() {
@@ -133,4 +152,72 @@ impl Database {
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    // 工作区目录表，按配置档案分组，当前档案保存在设置表中
+    pub fn init_workspace_roots_table(&self) -> Result<()> {
+        if !self.table_exists("workspace_roots") {
+            self.create_table(
+                "CREATE TABLE workspace_roots (
+                    profile TEXT NOT NULL,
+                    path TEXT NOT NULL,
+                    name TEXT,
+                    PRIMARY KEY (profile, path)
+                )"
+            )?;
+        }
+        self.init_app_settings_table()
+    }
+
+    // 应用设置表，保存键值对形式的设置
+    pub fn init_app_settings_table(&self) -> Result<()> {
+        if !self.table_exists("app_settings") {
+            self.create_table(
+                "CREATE TABLE app_settings (
+                    key TEXT PRIMARY KEY,
+                    value TEXT
+                )"
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn get_setting(&self, key: &str) -> Option<String> {
+        self.conn
+            .query_row("SELECT value FROM app_settings WHERE key = ?1", [key], |row| row.get(0))
+            .ok()
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<usize> {
+        self.conn.execute("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)", [key, value])
+    }
+
+    // 服务器工具列表缓存，tools 为 tools/list 返回的 JSON 数组
+    pub fn init_tool_cache_table(&self) -> Result<()> {
+        if !self.table_exists("tool_cache") {
+            self.create_table(
+                "CREATE TABLE tool_cache (
+                    server TEXT PRIMARY KEY,
+                    tools TEXT NOT NULL,
+                    updated_at INTEGER NOT NULL
+                )"
+            )?;
+        }
+        Ok(())
+    }
+
+    // 每个服务器工具的启用状态和别名，没有记录的工具默认启用
+    pub fn init_server_tools_table(&self) -> Result<()> {
+        if !self.table_exists("server_tools") {
+            self.create_table(
+                "CREATE TABLE server_tools (
+                    server TEXT NOT NULL,
+                    tool TEXT NOT NULL,
+                    enabled INTEGER NOT NULL DEFAULT 1,
+                    alias TEXT,
+                    PRIMARY KEY (server, tool)
+                )"
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+// 测试会修改 PATH、HOME 等进程级环境变量，需要依次执行
+static TEST_ENV_LOCK: Mutex<()> = Mutex::new(());
+
+// 测试期间独占进程环境，drop 时按相反顺序恢复修改过的环境变量
+pub struct TestEnv {
+    saved: Vec<(String, Option<OsString>)>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestEnv {
+    pub fn lock() -> TestEnv {
+        TestEnv {
+            saved: Vec::new(),
+            _lock: TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+
+    fn save(&mut self, key: &str) {
+        self.saved.push((key.to_string(), std::env::var_os(key)));
+    }
+
+    pub fn set(&mut self, key: &str, value: impl AsRef<OsStr>) {
+        self.save(key);
+        std::env::set_var(key, value);
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.save(key);
+        std::env::remove_var(key);
+    }
+
+    // 把 dir 放在 PATH 最前面，用于替换外部命令
+    pub fn prepend_path(&mut self, dir: &Path) {
+        let mut paths = vec![dir.to_path_buf()];
+        paths.extend(std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()));
+        self.set("PATH", std::env::join_paths(paths).unwrap());
+    }
+
+    // HOME 和数据目录指向 root 下的目录，rustup 和 cargo 仍使用原来的目录
+    pub fn isolate_home(&mut self, root: &Path) {
+        let home = dirs::home_dir().unwrap();
+        if std::env::var_os("RUSTUP_HOME").is_none() {
+            self.set("RUSTUP_HOME", home.join(".rustup"));
+        }
+        if std::env::var_os("CARGO_HOME").is_none() {
+            self.set("CARGO_HOME", home.join(".cargo"));
+        }
+        self.set("HOME", root.join("home"));
+        self.set("XDG_DATA_HOME", root.join("home").join("data"));
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        while let Some((key, value)) = self.saved.pop() {
+            match value {
+                Some(value) => std::env::set_var(&key, value),
+                None => std::env::remove_var(&key),
+            }
+        }
+    }
+}
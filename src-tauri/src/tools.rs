@@ -1,394 +1,1167 @@
-use std::process::Command;
+use std::cmp::Ordering as VersionOrdering;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
 use log::{info, error, warn};
+use serde::{Deserialize, Serialize};
 
-// 定义支持的工具
-pub enum Tool {
-    Uv,
-    Bun,
-    Git,
+// 内置工具注册表
+const BUILTIN_REGISTRY: &str = include_str!("../resources/tools.toml");
+
+// 工具注册表文件
+#[derive(Deserialize, Debug, Default)]
+struct ToolRegistryFile {
+    #[serde(default)]
+    tools: Vec<ToolSpec>,
+}
+
+// 工具描述
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ToolSpec {
+    pub id: String,
+    pub name: String,
+    // 可执行文件的候选名称，Windows 上会自动尝试 .exe 和 .cmd 后缀
+    #[serde(default)]
+    pub executables: Vec<String>,
+    // MCP 服务器配置中可以使用的命令，为空时使用 executables
+    #[serde(default)]
+    pub commands: Vec<String>,
+    // 检测探针，按顺序尝试
+    #[serde(default = "default_probes")]
+    pub probes: Vec<Probe>,
+    // 获取版本号的参数
+    #[serde(default = "default_version_args")]
+    pub version_args: Vec<String>,
+    // 最新（或固定）版本号的来源
+    #[serde(default)]
+    pub latest: Option<VersionSource>,
+    // 各系统的安装方式
+    #[serde(default)]
+    pub install: HashMap<String, InstallStrategy>,
+    // 安装前需要先安装的工具
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    // 是否包含在一键安装中
+    #[serde(default)]
+    pub auto_install: bool,
+    // 应用安装的文件或目录，卸载和升级时删除
+    #[serde(default)]
+    pub managed_paths: Vec<String>,
+}
+
+// 检测探针
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Probe {
+    // 在指定目录中查找可执行文件
+    Dir { path: String, os: Option<String> },
+    // 在 PATH 中查找可执行文件
+    Path,
+    // 执行已注册工具的命令，标准输出为可执行文件路径
+    Command { tool: String, args: Vec<String> },
+}
+
+// 版本号来源
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VersionSource {
+    // tag_prefix 为版本标签的前缀，如 bun 的 "bun-v"
+    GithubRelease {
+        repo: String,
+        #[serde(default)]
+        tag_prefix: Option<String>,
+    },
+    NodeLts,
+    Pinned { version: String },
+}
+
+// 安装方式
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InstallStrategy {
+    // 通过 sh -c 执行的脚本
+    Shell { script: String },
+    // 通过 PowerShell 执行的脚本
+    Powershell { script: String },
+    // 直接执行程序，tool 指向已注册工具时使用其可执行文件
+    Command {
+        program: Option<String>,
+        tool: Option<String>,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    // 无法自动安装，仅给出提示
+    Manual { hint: String },
+}
+
+fn default_probes() -> Vec<Probe> {
+    vec![Probe::Path]
+}
+
+fn default_version_args() -> Vec<String> {
+    vec!["--version".to_string()]
+}
+
+// 工具状态
+#[derive(Serialize, Debug)]
+pub struct ToolStatus {
+    pub name: String,
+    pub installed: bool,
+    pub version: Option<String>,
+    pub path: Option<String>,
+}
+
+// MCP 服务器的运行时状态
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum RuntimeStatus {
+    // 命令可用
+    Ready { tool: Option<String> },
+    // 命令由已注册的工具提供但尚未安装，可通过 install_single_tool 安装
+    MissingRuntime { tool: String, command: String },
+    // 命令不可用且没有对应的已注册工具
+    Unavailable { command: String },
+}
+
+// 应用托管的工具安装目录 (~/.omni-mcp/bin)
+pub fn app_bin_dir() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or_else(|| "无法获取用户主目录".to_string())?;
+    Ok(home.join(".omni-mcp").join("bin"))
+}
+
+// 用户自定义的工具注册表 (~/.omni-mcp/tools.toml)
+pub fn user_registry_path() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or_else(|| "无法获取用户主目录".to_string())?;
+    Ok(home.join(".omni-mcp").join("tools.toml"))
+}
+
+// 加载工具注册表，用户条目按 id 覆盖内置条目
+pub fn load_registry() -> Result<Vec<ToolSpec>, String> {
+    let builtin: ToolRegistryFile = toml::from_str(BUILTIN_REGISTRY)
+        .map_err(|e| format!("解析内置工具注册表失败: {}", e))?;
+    let mut tools = builtin.tools;
+
+    let user_path = user_registry_path()?;
+    if user_path.exists() {
+        let content = std::fs::read_to_string(&user_path)
+            .map_err(|e| format!("读取用户工具注册表失败: {}", e))?;
+        let user: ToolRegistryFile = toml::from_str(&content)
+            .map_err(|e| format!("解析用户工具注册表 {:?} 失败: {}", user_path, e))?;
+
+        for spec in user.tools {
+            match tools.iter_mut().find(|tool| tool.id == spec.id) {
+                Some(existing) => *existing = spec,
+                None => tools.push(spec),
+            }
+        }
+    }
+
+    Ok(tools)
+}
+
+// 根据 id 查找已注册的工具
+pub fn find_tool(id: &str) -> Result<ToolSpec, String> {
+    load_registry()?
+        .into_iter()
+        .find(|tool| tool.id == id)
+        .ok_or_else(|| format!("未注册的工具: {}", id))
+}
+
+// 查找提供指定命令的工具，command 可以是命令名或完整路径
+pub fn tool_for_command(command: &str) -> Result<Option<ToolSpec>, String> {
+    let file_name = std::path::Path::new(command)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| command.to_string());
+    let name = file_name.trim_end_matches(".exe").trim_end_matches(".cmd");
+
+    Ok(load_registry()?
+        .into_iter()
+        .find(|tool| tool.provides_command(name)))
+}
+
+// 解析 MCP 服务器的启动命令，注册表中工具的命令使用检测到的路径，
+// 这样安装在应用目录中、尚未加入 PATH 的工具也能启动
+pub fn resolve_command(command: &str) -> PathBuf {
+    let path = Path::new(command);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let Some(executable) = tool_for_command(command).ok().flatten().and_then(|tool| tool.find_executable()) else {
+        return path.to_path_buf();
+    };
+
+    // uvx、npx 等命令与工具的可执行文件位于同一目录
+    let Some(dir) = executable.parent() else {
+        return executable;
+    };
+    let suffixes: &[&str] = if cfg!(target_os = "windows") { &["", ".exe", ".cmd"] } else { &[""] };
+    suffixes.iter()
+        .map(|suffix| dir.join(format!("{}{}", command, suffix)))
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+// 检查 MCP 服务器启动命令所需的运行时是否可用
+pub fn check_runtime(command: &str) -> RuntimeStatus {
+    match tool_for_command(command) {
+        Ok(Some(tool)) => {
+            if tool.check_installed() {
+                RuntimeStatus::Ready { tool: Some(tool.id) }
+            } else {
+                RuntimeStatus::MissingRuntime { tool: tool.id, command: command.to_string() }
+            }
+        },
+        Ok(None) | Err(_) => {
+            let path = std::path::Path::new(command);
+            if (path.is_absolute() && path.exists()) || find_in_path(command).is_some() {
+                RuntimeStatus::Ready { tool: None }
+            } else {
+                RuntimeStatus::Unavailable { command: command.to_string() }
+            }
+        },
+    }
+}
+
+// 当前系统在注册表中的键，按优先级排列
+fn os_keys() -> &'static [&'static str] {
+    if cfg!(target_os = "windows") {
+        &["windows", "all"]
+    } else if cfg!(target_os = "macos") {
+        &["macos", "unix", "all"]
+    } else {
+        &["linux", "unix", "all"]
+    }
+}
+
+// 从 `--version` 输出中提取版本号，例如 "git version 2.43.0" -> "2.43.0"
+fn parse_version(output: &str) -> Option<String> {
+    let first_line = output.lines().next()?.trim();
+    for token in first_line.split_whitespace() {
+        let token = token.trim_start_matches('v').trim_end_matches(|c: char| !c.is_ascii_alphanumeric());
+        if token.starts_with(|c: char| c.is_ascii_digit()) && token.contains('.') {
+            return Some(token.to_string());
+        }
+    }
+    if first_line.is_empty() {
+        None
+    } else {
+        Some(first_line.to_string())
+    }
+}
+
+// Node.js 风格的平台和架构名称
+fn node_platform() -> (&'static str, &'static str) {
+    let os = if cfg!(target_os = "windows") {
+        "win"
+    } else if cfg!(target_os = "macos") {
+        "darwin"
+    } else {
+        "linux"
+    };
+    let arch = match std::env::consts::ARCH {
+        "aarch64" => "arm64",
+        _ => "x64",
+    };
+    (os, arch)
+}
+
+// 替换脚本中的占位符
+pub fn expand_placeholders(text: &str, version: Option<&str>) -> Result<String, String> {
+    let home = dirs::home_dir().ok_or_else(|| "无法获取用户主目录".to_string())?;
+    let (os, arch) = node_platform();
+
+    let mut expanded = text
+        .replace("{home}", &home.to_string_lossy())
+        .replace("{app_bin}", &app_bin_dir()?.to_string_lossy())
+        .replace("{os}", os)
+        .replace("{arch}", arch);
+
+    if let Some(version) = version {
+        expanded = expanded.replace("{version}", version);
+    }
+
+    Ok(expanded)
+}
+
+// 在 PATH 中查找可执行文件
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let finder = if cfg!(target_os = "windows") { "where" } else { "which" };
+    let output = Command::new(finder).arg(name).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| PathBuf::from(line.trim()))
+}
+
+// 构造带代理设置的 curl 命令
+pub fn curl_command(url: &str) -> Command {
+    let mut command = Command::new("curl");
+    command.args([
+        "-s",
+        "-L", // 添加跟随重定向
+        "--connect-timeout", "10", // 添加超时设置
+        url
+    ]);
+
+    // 如果存在系统代理，添加代理支持
+    if let Ok(proxy) = std::env::var("HTTPS_PROXY").or_else(|_| std::env::var("https_proxy")) {
+        command.args(["--proxy", &proxy]);
+    }
+
+    command
 }
 
-impl Tool {
-    // 获取工具的名称
-    fn name(&self) -> &str {
+impl VersionSource {
+    // 获取版本号
+    pub fn resolve(&self) -> Result<String, String> {
         match self {
-            Tool::Uv => "uv",
-            Tool::Bun => "bun",
-            Tool::Git => "git",
+            VersionSource::Pinned { version } => Ok(version.clone()),
+            VersionSource::GithubRelease { repo, tag_prefix } => {
+                println!("正在获取 {} 最新版本号...", repo);
+                let output = curl_command(&format!("https://api.github.com/repos/{}/releases/latest", repo))
+                    .output()
+                    .map_err(|e| format!("获取 {} 版本失败: {}", repo, e))?;
+
+                let release: serde_json::Value = serde_json::from_slice(&output.stdout)
+                    .map_err(|e| format!("解析 {} 版本信息失败: {}", repo, e))?;
+
+                release.get("tag_name")
+                    .and_then(|tag| tag.as_str())
+                    .map(|tag| {
+                        println!("解析到的版本号: {}", tag);
+                        let prefix = tag_prefix.as_deref().unwrap_or_default();
+                        tag.strip_prefix(prefix).unwrap_or(tag).to_string()
+                    })
+                    .ok_or_else(|| format!("无法解析 {} 版本信息", repo))
+            },
+            VersionSource::NodeLts => {
+                println!("正在获取 Node.js 最新 LTS 版本号...");
+                let output = curl_command("https://nodejs.org/dist/index.json")
+                    .output()
+                    .map_err(|e| format!("获取 Node.js 版本失败: {}", e))?;
+
+                let releases: serde_json::Value = serde_json::from_slice(&output.stdout)
+                    .map_err(|e| format!("解析 Node.js 版本信息失败: {}", e))?;
+
+                // index.json 按发布时间倒序排列，lts 字段为 false 表示非 LTS 版本
+                releases.as_array()
+                    .and_then(|list| list.iter().find(|release| {
+                        release.get("lts").is_some_and(|lts| !lts.is_boolean())
+                    }))
+                    .and_then(|release| release.get("version"))
+                    .and_then(|version| version.as_str())
+                    .map(|version| version.trim_start_matches('v').to_string())
+                    .ok_or_else(|| "无法解析 Node.js 版本信息".to_string())
+            },
         }
     }
-    
-    // 检查工具是否已安装
-    fn check_installed(&self) -> bool {
+}
+
+impl ToolSpec {
+    // 可执行文件的候选名称
+    fn executable_names(&self) -> Vec<String> {
+        let names = if self.executables.is_empty() {
+            vec![self.id.clone()]
+        } else {
+            self.executables.clone()
+        };
+
         if cfg!(target_os = "windows") {
-            match self {
-                Tool::Git => {
-                    // Git 使用 where 命令检查
-                    Command::new("where")
-                        .arg(self.name())
-                        .output()
-                        .map_or(false, |output| output.status.success())
+            // Windows 上 npx 等工具是 .cmd 脚本
+            names.iter()
+                .flat_map(|name| vec![format!("{}.exe", name), format!("{}.cmd", name)])
+                .collect()
+        } else {
+            names
+        }
+    }
+
+    // 是否提供指定的命令
+    fn provides_command(&self, command: &str) -> bool {
+        if self.commands.is_empty() {
+            self.executables.iter().any(|name| name == command) || self.id == command
+        } else {
+            self.commands.iter().any(|name| name == command)
+        }
+    }
+
+    // 依次执行检测探针，查找工具的可执行文件路径
+    pub fn find_executable(&self) -> Option<PathBuf> {
+        let names = self.executable_names();
+
+        for probe in &self.probes {
+            match probe {
+                Probe::Dir { path, os } => {
+                    if let Some(os) = os {
+                        if !os_keys().contains(&os.as_str()) {
+                            continue;
+                        }
+                    }
+                    let Ok(dir) = expand_placeholders(path, None) else {
+                        continue;
+                    };
+                    let dir = PathBuf::from(dir);
+                    for name in &names {
+                        let candidate = dir.join(name);
+                        if candidate.exists() {
+                            return Some(candidate);
+                        }
+                    }
                 },
-                Tool::Bun => {
-                    // 检查 Bun 的安装目录
-                    let home = std::env::var("USERPROFILE").unwrap_or_default();
-                    let bun_path = format!("{}/.bun/bin/bun.exe", home);
-                    std::path::Path::new(&bun_path).exists()
+                Probe::Path => {
+                    for name in &names {
+                        // where 和 which 都不需要后缀
+                        let lookup = name.trim_end_matches(".exe").trim_end_matches(".cmd");
+                        if let Some(path) = find_in_path(lookup) {
+                            return Some(path);
+                        }
+                    }
                 },
-                Tool::Uv => {
-                    // 检查 UV 的安装目录
-                    let home = std::env::var("USERPROFILE").unwrap_or_default();
-                    let uv_path = format!("{}/.local/bin/uv.exe", home);
-                    std::path::Path::new(&uv_path).exists()
+                Probe::Command { tool, args } => {
+                    let Some(program) = find_tool(tool).ok().and_then(|spec| spec.find_executable()) else {
+                        continue;
+                    };
+                    let Ok(output) = Command::new(program).args(args).output() else {
+                        continue;
+                    };
+                    if output.status.success() {
+                        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                        if !path.is_empty() {
+                            return Some(PathBuf::from(path));
+                        }
+                    }
                 },
             }
-        } else {
-            // Unix 系统保持原样
-            Command::new("which")
-                .arg(self.name())
-                .output()
-                .map_or(false, |output| output.status.success())
         }
+
+        None
     }
 
-    // 获取 UV 最新版本号
-    fn get_uv_latest_version() -> Result<String, String> {
-        println!("正在获取 UV 最新版本号...");
-        let mut command = Command::new("curl");
-        command.args([
-            "-s",
-            "-L", // 添加跟随重定向
-            "--connect-timeout", "10", // 添加超时设置
-            "https://api.github.com/repos/astral-sh/uv/releases/latest"
-        ]);
-
-          // 如果存在系统代理，添加代理支持
-        if let Ok(proxy) = std::env::var("HTTPS_PROXY").or_else(|_| std::env::var("https_proxy")) {
-            command.args(["--proxy", &proxy]);
+    // 在 PATH 的所有目录中查找可执行文件，用于检测多个版本冲突
+    pub fn find_all_in_path(&self) -> Vec<PathBuf> {
+        let mut found: Vec<PathBuf> = Vec::new();
+        let mut seen: Vec<PathBuf> = Vec::new();
+        let Some(paths) = std::env::var_os("PATH") else {
+            return found;
+        };
+
+        for dir in std::env::split_paths(&paths) {
+            for name in self.executable_names() {
+                let candidate = dir.join(&name);
+                if !candidate.is_file() {
+                    continue;
+                }
+                // /bin 和 /usr/bin 等符号链接指向同一个文件时只算一个
+                let canonical = candidate.canonicalize().unwrap_or_else(|_| candidate.clone());
+                if !seen.contains(&canonical) {
+                    seen.push(canonical);
+                    found.push(candidate);
+                }
+            }
         }
 
-        let output = command
-        .output()
-        .map_err(|e| format!("获取UV版本失败: {}", e))?;
+        found
+    }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        // println!("GitHub API 响应:\n{}", stdout);
-        
-        // 直接查找 "tag_name": "x.x.x" 格式
-        if let Some(tag_pos) = stdout.find("\"tag_name\": \"") {
-            let start = tag_pos + "\"tag_name\": \"".len();
-            if let Some(end) = stdout[start..].find('"') {
-                let version = &stdout[start..start + end];
-                println!("解析到的版本号: {}", version);
-                return Ok(version.to_string());
+    // 已存在的托管路径及其在注册表中的写法，Windows 上自动尝试 .exe 和 .cmd 后缀
+    pub fn managed_path_entries(&self) -> Vec<(String, PathBuf)> {
+        let mut entries = Vec::new();
+        for entry in &self.managed_paths {
+            let Ok(expanded) = expand_placeholders(entry, None) else {
+                continue;
+            };
+            let path = PathBuf::from(&expanded);
+            if path.exists() {
+                entries.push((entry.clone(), path));
+            } else if cfg!(target_os = "windows") {
+                for suffix in [".exe", ".cmd"] {
+                    let candidate = PathBuf::from(format!("{}{}", expanded, suffix));
+                    if candidate.exists() {
+                        entries.push((format!("{}{}", entry, suffix), candidate));
+                    }
+                }
             }
         }
-        
-        let err_msg = "无法解析UV版本信息".to_string();
-        println!("错误: {}", err_msg);
-        Err(err_msg)
+        entries
+    }
+
+    // 展开注册表中该工具的托管路径写法，不属于该工具的写法返回 None
+    //
+    // 用于还原离线安装包，Windows 上允许 managed_path_entries 加上的 .exe 和 .cmd 后缀
+    pub fn managed_path_target(&self, entry: &str) -> Option<PathBuf> {
+        let suffixes: &[&str] = if cfg!(target_os = "windows") { &["", ".exe", ".cmd"] } else { &[""] };
+        let known = self.managed_paths.iter()
+            .any(|managed| suffixes.iter().any(|suffix| format!("{}{}", managed, suffix) == entry));
+        if !known {
+            return None;
+        }
+        expand_placeholders(entry, None).ok().map(PathBuf::from)
     }
-    
-    // 获取安装命令
-    fn install_command(&self) -> Result<(String, Vec<String>), String> {
-        if self.check_installed() {
-            return Err(format!("{} 已经安装", self.name()));
+
+    // 已存在的托管路径
+    fn existing_managed_paths(&self) -> Vec<PathBuf> {
+        self.managed_path_entries().into_iter().map(|(_, path)| path).collect()
+    }
+
+    // 检测到的可执行文件是否由应用安装（位于托管路径下）
+    pub fn is_managed(&self, executable: &Path) -> bool {
+        self.managed_paths.iter()
+            .filter_map(|entry| expand_placeholders(entry, None).ok())
+            .any(|entry| executable.starts_with(&entry)
+                || executable.with_extension("") == Path::new(&entry))
+    }
+
+    // 未安装时给用户的安装建议
+    pub fn install_hint(&self) -> String {
+        match self.install_strategy() {
+            Some(InstallStrategy::Manual { hint }) => hint.clone(),
+            Some(_) => format!("可在工具管理页面安装 {}", self.name),
+            None if !self.depends_on.is_empty() => {
+                format!("安装 {} 后即可使用 {}", self.depends_on.join(", "), self.name)
+            },
+            None => format!("当前系统不支持自动安装 {}，请手动安装", self.name),
         }
-    
-        if cfg!(target_os = "windows") {
-            match self {
-                Tool::Uv => {
-                    match Self::get_uv_latest_version() {
-                        Ok(version) => {
-                            println!("成功获取 UV 最新版本: {}", version);
-                            Ok((
-                                "powershell".to_string(),
-                                vec![
-                                    "-ExecutionPolicy".to_string(),
-                                    "Bypass".to_string(),
-                                    "-c".to_string(),
-                                    format!(
-                                        "$ErrorActionPreference = 'Stop';
-                                        Write-Host '正在下载 UV 安装脚本...';
-                                        $progressPreference = 'silentlyContinue';
-                                        curl.exe -L -s -o uv-installer.ps1 https://github.com/astral-sh/uv/releases/download/{}/uv-installer.ps1;
-                                        if ($LastExitCode -eq 0) {{
-                                            Write-Host '开始安装 UV...';
-                                            $env:UV_INSTALL_QUIET = 1;
-                                            .\\uv-installer.ps1;
-                                            if ($LastExitCode -eq 0) {{
-                                                Write-Host '正在更新环境变量...';
-                                                $env:Path = [System.Environment]::GetEnvironmentVariable('Path', 'User') + ';' + $env:USERPROFILE + '\\.local\\bin';
-                                                [System.Environment]::SetEnvironmentVariable('Path', $env:Path, 'User');
-                                                Write-Host 'UV 安装成功！';
-                                                Remove-Item uv-installer.ps1;
-                                            }} else {{
-                                                throw 'UV 安装失败'
-                                            }}
-                                        }} else {{
-                                            throw '下载安装脚本失败'
-                                        }}",
-                                        version
-                                    ),
-                                ]
-                            ))
-                        },
-                        Err(e) => Err(format!("获取 UV 版本失败: {}", e))
-                    }
-                },
-                Tool::Bun => Ok((
-                    "powershell".to_string(),
-                    vec![
-                        "-ExecutionPolicy".to_string(),
-                        "Bypass".to_string(),
-                        "-c".to_string(),
-                        "$ErrorActionPreference = 'Stop';
-                        Write-Host '正在下载 Bun 安装脚本...';
-                        $progressPreference = 'silentlyContinue';
-                        curl.exe -L -s -o bun-installer.ps1 https://bun.sh/install.ps1;
-                        if ($LastExitCode -eq 0) {
-                            Write-Host '开始安装 Bun...';
-                            $BunRoot = \"$env:USERPROFILE\\.bun\";
-                            $env:BUN_INSTALL = $BunRoot;
-                            .\\bun-installer.ps1;
-                            if ($LastExitCode -eq 0) {
-                                Write-Host '正在更新环境变量...';
-                                $BunBinPath = \"$BunRoot\\bin\";
-                                $CurrentPath = [System.Environment]::GetEnvironmentVariable('Path', 'User');
-                                if (-not ($CurrentPath -like \"*$BunBinPath*\")) {
-                                    $NewPath = \"$CurrentPath;$BunBinPath\";
-                                    [System.Environment]::SetEnvironmentVariable('Path', $NewPath, 'User');
-                                }
-                                Write-Host '清理安装文件...';
-                                Remove-Item bun-installer.ps1;
-                                Write-Host 'Bun 安装成功！'
-                            } else {
-                                throw 'Bun 安装失败'
-                            }
-                        } else {
-                            throw '下载安装脚本失败'
-                        }".to_string(),
-                    ]
-                )),
-                Tool::Git => Ok((
-                    "powershell".to_string(),
-                    vec![
-                        "-ExecutionPolicy".to_string(),
-                        "Bypass".to_string(),
-                        "-c".to_string(),
-                        "$ErrorActionPreference = 'Stop';
-                        Write-Host '正在下载 Git 安装程序...';
-                        $progressPreference = 'silentlyContinue';
-                        curl.exe -L -s -o git-installer.exe https://github.com/git-for-windows/git/releases/download/v2.43.0.windows.1/Git-2.43.0-64-bit.exe;
-                        if ($LastExitCode -eq 0) {
-                            Write-Host '开始安装 Git...';
-                            .\\git-installer.exe /VERYSILENT /NORESTART /COMPONENTS='icons,ext\\reg\\shellhere,assoc,assoc_sh';
-                            if ($LastExitCode -eq 0) {
-                                Write-Host '正在更新环境变量...';
-                                $env:Path = [System.Environment]::GetEnvironmentVariable('Path', 'Machine');
-                                [System.Environment]::SetEnvironmentVariable('Path', $env:Path, 'Machine');
-                                Write-Host '清理安装文件...';
-                                Remove-Item git-installer.exe;
-                                Write-Host 'Git 安装成功！'
-                            } else {
-                                throw 'Git 安装失败'
-                            }
-                        } else {
-                            throw '下载安装程序失败'
-                        }".to_string(),
-                    ]
-                )),
-            }
+    }
+
+    // 由依赖提供的工具（如 npx 随 Node.js 安装）没有自己的安装步骤
+    fn provided_by_dependency(&self) -> bool {
+        self.install.is_empty() && !self.depends_on.is_empty()
+    }
+
+    // 由依赖提供的工具不能单独卸载或升级，提示用户操作提供它的工具
+    fn provider_hint(&self, action: &str) -> String {
+        let providers = self.depends_on.join(", ");
+        format!("{} 随 {} 安装，无法单独{}，请{} {}", self.name, providers, action, action, providers)
+    }
+
+    // 检查工具是否已安装
+    pub fn check_installed(&self) -> bool {
+        self.find_executable().is_some()
+    }
+
+    // 获取已安装工具的版本号
+    pub fn version(&self) -> Option<String> {
+        let path = self.find_executable()?;
+        let output = Command::new(&path).args(&self.version_args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // 部分旧版 Python 会把版本输出到 stderr
+        let text = if stdout.trim().is_empty() {
+            String::from_utf8_lossy(&output.stderr).to_string()
         } else {
-            // Unix-like systems (macOS, Linux)
-            match self {
-                Tool::Uv => {
-                    // 先获取版本号
-                    match Self::get_uv_latest_version() {
-                        Ok(version) => {
-                            println!("成功获取 UV 最新版本: {}", version);
-                            Ok((
-                                "curl".to_string(),
-                                vec![
-                                    "--proto".to_string(),
-                                    "=https".to_string(),
-                                    "--tlsv1.2".to_string(),
-                                    "-LsSf".to_string(),
-                                    format!("https://github.com/astral-sh/uv/releases/download/{}/uv-installer.sh",
-                                        version),
-                                    "|".to_string(),
-                                    "bash".to_string()
-                                ]
-                            ))
-                        },
-                        Err(e) => Err(format!("获取 UV 版本失败: {}", e))
-                    }
-                },
-                Tool::Bun => Ok((
-                    "curl".to_string(),
-                    vec![
-                        "-fsSL".to_string(),
-                        "https://bun.sh/install".to_string(),
-                        "|".to_string(),
-                        "bash".to_string()
-                    ]
-                )),
-                Tool::Git => {
-                    if cfg!(target_os = "macos") {
-                        Ok((
-                            "brew".to_string(),
-                            vec![
-                                "install".to_string(),
-                                "git".to_string()
-                            ]
-                        ))
-                    } else {
-                        // Linux
-                        Ok((
-                            "apt-get".to_string(),
-                            vec![
-                                "install".to_string(),
-                                "-y".to_string(),
-                                "git".to_string()
-                            ]
-                        ))
+            stdout.to_string()
+        };
+        parse_version(&text)
+    }
+
+    // 获取工具状态
+    pub fn status(&self) -> ToolStatus {
+        match self.find_executable() {
+            Some(path) => ToolStatus {
+                name: self.name.clone(),
+                installed: true,
+                version: self.version(),
+                path: Some(path.to_string_lossy().to_string()),
+            },
+            None => ToolStatus {
+                name: self.name.clone(),
+                installed: false,
+                version: None,
+                path: None,
+            },
+        }
+    }
+
+    // 当前系统对应的安装方式
+    fn install_strategy(&self) -> Option<&InstallStrategy> {
+        os_keys().iter().find_map(|key| self.install.get(*key))
+    }
+
+    // 获取安装命令，version 为空时按 latest 获取版本号
+    fn install_command(&self, version: Option<&str>) -> Result<Command, String> {
+        let strategy = self.install_strategy()
+            .ok_or_else(|| format!("{} 不支持在当前系统上自动安装", self.name))?;
+
+        // 仅在需要时获取版本号，避免无谓的网络请求
+        let needs_version = match strategy {
+            InstallStrategy::Shell { script } | InstallStrategy::Powershell { script } => script.contains("{version}"),
+            InstallStrategy::Command { args, .. } => args.iter().any(|arg| arg.contains("{version}")),
+            InstallStrategy::Manual { .. } => false,
+        };
+        let version = if let Some(version) = version.filter(|_| needs_version) {
+            Some(version.to_string())
+        } else if needs_version {
+            let source = self.latest.as_ref()
+                .ok_or_else(|| format!("{} 缺少版本号来源", self.name))?;
+            let version = source.resolve()?;
+            println!("成功获取 {} 版本: {}", self.name, version);
+            Some(version)
+        } else {
+            None
+        };
+        let version = version.as_deref();
+
+        match strategy {
+            InstallStrategy::Shell { script } => {
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(expand_placeholders(script, version)?);
+                Ok(command)
+            },
+            InstallStrategy::Powershell { script } => {
+                let mut command = Command::new("powershell");
+                command.args(["-ExecutionPolicy", "Bypass", "-c"])
+                    .arg(expand_placeholders(script, version)?);
+                Ok(command)
+            },
+            InstallStrategy::Command { program, tool, args } => {
+                let program = match (tool, program) {
+                    (Some(tool), _) => find_tool(tool)?
+                        .find_executable()
+                        .ok_or_else(|| format!("安装 {} 需要先安装 {}", self.name, tool))?,
+                    (None, Some(program)) => PathBuf::from(program),
+                    (None, None) => return Err(format!("{} 的安装命令缺少 program", self.name)),
+                };
+                let mut command = Command::new(program);
+                for arg in args {
+                    command.arg(expand_placeholders(arg, version)?);
+                }
+                Ok(command)
+            },
+            InstallStrategy::Manual { hint } => Err(hint.clone()),
+        }
+    }
+}
+
+// 安装进度
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "stage", rename_all = "camelCase")]
+pub enum InstallProgress {
+    Started,
+    Download {
+        percent: Option<f32>,
+        downloaded: Option<u64>,
+        total: Option<u64>,
+    },
+    Extract,
+    Install,
+    Log { message: String },
+    Verify,
+    Done { version: Option<String> },
+    Failed { error: String },
+    Cancelled,
+}
+
+// 将 curl 输出的大小（如 12.3M）转换为字节数
+fn parse_size(text: &str) -> Option<u64> {
+    let (number, unit) = match text.chars().last()? {
+        'k' | 'K' => (&text[..text.len() - 1], 1024f64),
+        'M' => (&text[..text.len() - 1], 1024f64 * 1024.0),
+        'G' => (&text[..text.len() - 1], 1024f64 * 1024.0 * 1024.0),
+        _ => (text, 1f64),
+    };
+    number.parse::<f64>().ok().map(|value| (value * unit) as u64)
+}
+
+// 解析安装输出中的进度信息
+fn parse_progress_line(line: &str) -> Option<InstallProgress> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    // curl -# 进度条: "######            45.3%"
+    if trimmed.starts_with('#') && trimmed.ends_with('%') {
+        let percent = trimmed.split_whitespace().last()?.trim_end_matches('%').parse().ok();
+        return Some(InstallProgress::Download { percent, downloaded: None, total: None });
+    }
+
+    // curl 默认进度表: "% Total % Received % Xferd Average Speed Time Time Time Current"
+    let columns: Vec<&str> = trimmed.split_whitespace().collect();
+    if columns.len() >= 12 && columns[9].contains(':') {
+        if let Ok(percent) = columns[0].parse::<f32>() {
+            return Some(InstallProgress::Download {
+                percent: Some(percent),
+                downloaded: parse_size(columns[3]),
+                total: parse_size(columns[1]),
+            });
+        }
+    }
+    if trimmed.starts_with("% Total") || trimmed.starts_with("Dload") {
+        return None;
+    }
+
+    if trimmed.contains("正在下载") || trimmed.starts_with("Downloading") {
+        return Some(InstallProgress::Download { percent: None, downloaded: None, total: None });
+    }
+    if trimmed.contains("正在解压") || trimmed.starts_with("Extracting") {
+        return Some(InstallProgress::Extract);
+    }
+    if trimmed.contains("开始安装") || trimmed.starts_with("Installing") {
+        return Some(InstallProgress::Install);
+    }
+
+    Some(InstallProgress::Log { message: trimmed.to_string() })
+}
+
+// 在后台线程中按行读取输出，curl 的进度使用 \r 刷新，因此同时按 \r 分行
+fn stream_lines<R: Read + Send + 'static>(mut reader: R, sender: mpsc::Sender<String>) {
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; 4096];
+        let mut line = Vec::new();
+        while let Ok(count) = reader.read(&mut buffer) {
+            if count == 0 {
+                break;
+            }
+            for &byte in &buffer[..count] {
+                if byte == b'\n' || byte == b'\r' {
+                    if !line.is_empty() {
+                        let _ = sender.send(String::from_utf8_lossy(&line).to_string());
+                        line.clear();
                     }
-                },
+                } else {
+                    line.push(byte);
+                }
             }
         }
+        if !line.is_empty() {
+            let _ = sender.send(String::from_utf8_lossy(&line).to_string());
+        }
+    });
+}
+
+// 结束进程及其子进程，进程需要以独立进程组启动
+pub fn kill_process_tree(child: &mut Child) {
+    kill_process_group(child.id());
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// 检查进程是否仍在运行
+pub fn process_alive(pid: u32) -> bool {
+    if cfg!(target_os = "windows") {
+        Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+    } else {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .output()
+            .is_ok_and(|output| output.status.success())
     }
 }
 
-// 安装工具
-pub fn install_tool(tool: &Tool) -> Result<(), String> {
-    println!("开始安装 {}", tool.name());
-    info!("开始安装 {}", tool.name());
-    
-    let (cmd, args) = tool.install_command()?;
-    println!("执行命令: {} {}", cmd, args.join(" "));
-    
+// 读取进程的命令行，进程不存在时返回 None
+pub fn process_command_line(pid: u32) -> Option<String> {
     let output = if cfg!(target_os = "windows") {
-        Command::new(&cmd)
-            .args(&args)
+        Command::new("powershell")
+            .args(["-NoProfile", "-Command", &format!("(Get-CimInstance Win32_Process -Filter 'ProcessId={}').CommandLine", pid)])
             .output()
     } else {
-        // 对于 Unix 系统，使用 sh -c 来执行完整命令
-        let full_command = format!("{} {}", cmd, args.join(" "));
-        Command::new("sh")
-            .arg("-c")
-            .arg(&full_command)
-            .output()
+        Command::new("ps").args(["-p", &pid.to_string(), "-o", "args="]).output()
+    }.ok()?;
+    let command_line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !command_line.is_empty()).then_some(command_line)
+}
+
+// 按 pid 结束进程及其子进程，用于结束其他进程启动的服务器
+pub fn kill_process_group(pid: u32) {
+    let pid = pid.to_string();
+    if cfg!(target_os = "windows") {
+        let _ = Command::new("taskkill").args(["/T", "/F", "/PID", &pid]).output();
+    } else {
+        // 向整个进程组发送信号
+        let _ = Command::new("kill").args(["-TERM", &format!("-{}", pid)]).output();
+    }
+}
+
+// 安装工具，先安装缺失的依赖
+//
+// on_progress 的第一个参数为正在安装的工具 id（可能是依赖），cancel 被置位时结束安装进程
+pub fn install_tool(
+    tool: &ToolSpec,
+    on_progress: &dyn Fn(&str, InstallProgress),
+    cancel: &AtomicBool,
+) -> Result<(), String> {
+    install_tool_with_deps(tool, None, false, on_progress, cancel, &mut Vec::new())
+}
+
+// reinstall 为 true 时用于升级，只检查托管路径，PATH 中其他位置的同名工具不影响安装
+fn install_tool_with_deps(
+    tool: &ToolSpec,
+    version: Option<&str>,
+    reinstall: bool,
+    on_progress: &dyn Fn(&str, InstallProgress),
+    cancel: &AtomicBool,
+    visiting: &mut Vec<String>,
+) -> Result<(), String> {
+    let fail = |err_msg: String| -> Result<(), String> {
+        on_progress(&tool.id, InstallProgress::Failed { error: err_msg.clone() });
+        Err(err_msg)
     };
-    
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            
-            // 打印安装过程输出
-            if !stdout.is_empty() {
-                println!("安装输出:\n{}", stdout);
-            }
-            
-            // 过滤掉进度条相关的输出
-            let filtered_stderr = stderr.lines()
-                .filter(|line| !line.contains("##") && !line.contains("%"))
-                .collect::<Vec<&str>>()
-                .join("\n");
-            
-            if !filtered_stderr.is_empty() {
-                println!("安装错误输出:\n{}", filtered_stderr);
-            }
-            
-            if !output.status.success() {
-                let err_msg = format!("安装 {} 失败", tool.name());
-                error!("{}", err_msg);
-                return Err(err_msg);
-            }
-            
-            println!("{} 安装完成！", tool.name());
-            info!("{} 安装完成", tool.name());
-            Ok(())
-        },
-        Err(e) => {
-            let err_msg = format!("安装 {} 时出错: {}", tool.name(), e);
-            println!("错误: {}", err_msg);
-            error!("{}", err_msg);
-            Err(err_msg)
+
+    let installed = if reinstall {
+        !tool.existing_managed_paths().is_empty()
+    } else {
+        tool.check_installed()
+    };
+    if installed {
+        return fail(format!("{} 已经安装", tool.name));
+    }
+
+    if visiting.contains(&tool.id) {
+        return fail(format!("工具依赖存在循环: {} -> {}", visiting.join(" -> "), tool.id));
+    }
+    visiting.push(tool.id.clone());
+
+    for dep_id in &tool.depends_on {
+        let dep = match find_tool(dep_id) {
+            Ok(dep) => dep,
+            Err(e) => return fail(e),
+        };
+        if !dep.check_installed() {
+            println!("{} 依赖 {}，先安装 {}", tool.name, dep.name, dep.name);
+            install_tool_with_deps(&dep, None, false, on_progress, cancel, visiting)?;
         }
     }
+
+    visiting.pop();
+
+    if tool.provided_by_dependency() {
+        on_progress(&tool.id, InstallProgress::Verify);
+        return match tool.find_executable() {
+            Some(_) => {
+                on_progress(&tool.id, InstallProgress::Done { version: tool.version() });
+                Ok(())
+            },
+            None => fail(format!("安装依赖后仍未找到 {}", tool.name)),
+        };
+    }
+
+    run_install(tool, version, on_progress, cancel)
 }
 
-// 检查并安装所有工具
-#[tauri::command] // <-- Add this attribute
-pub fn check_and_install_tools() -> Result<(), String> {
-    info!("开始安装工具");
+// 执行安装命令，逐行解析输出并上报进度
+fn run_install(
+    tool: &ToolSpec,
+    version: Option<&str>,
+    on_progress: &dyn Fn(&str, InstallProgress),
+    cancel: &AtomicBool,
+) -> Result<(), String> {
+    println!("开始安装 {}", tool.name);
+    info!("开始安装 {}", tool.name);
+    on_progress(&tool.id, InstallProgress::Started);
+
+    let fail = |err_msg: String| -> Result<(), String> {
+        println!("错误: {}", err_msg);
+        error!("{}", err_msg);
+        on_progress(&tool.id, InstallProgress::Failed { error: err_msg.clone() });
+        Err(err_msg)
+    };
+
+    let mut command = match tool.install_command(version) {
+        Ok(command) => command,
+        Err(e) => return fail(e),
+    };
+    println!("执行命令: {:?}", command);
+
+    command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return fail(format!("安装 {} 时出错: {}", tool.name, e)),
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        stream_lines(stdout, sender.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        stream_lines(stderr, sender);
+    }
 
-    // 安装 uv
-    println!("准备安装 uv...");
-    match install_tool(&Tool::Uv) {
-        Ok(_) => info!("uv 安装成功"),
-        Err(e) => warn!("uv 安装失败: {}", e),
+    // 保留最近的输出，安装失败时作为错误信息
+    let mut recent_logs: Vec<String> = Vec::new();
+    let mut handle_line = |line: String| {
+        match parse_progress_line(&line) {
+            Some(InstallProgress::Log { message }) => {
+                println!("安装输出: {}", message);
+                recent_logs.push(message.clone());
+                if recent_logs.len() > 10 {
+                    recent_logs.remove(0);
+                }
+                on_progress(&tool.id, InstallProgress::Log { message });
+            },
+            Some(progress) => on_progress(&tool.id, progress),
+            None => {},
+        }
+    };
+
+    let status = loop {
+        if cancel.load(Ordering::SeqCst) {
+            kill_process_tree(&mut child);
+            let err_msg = format!("已取消安装 {}", tool.name);
+            warn!("{}", err_msg);
+            on_progress(&tool.id, InstallProgress::Cancelled);
+            return Err(err_msg);
+        }
+
+        match receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(line) => handle_line(line),
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => {
+                // 输出已经结束，等待进程退出
+                std::thread::sleep(Duration::from_millis(50));
+            },
+        }
+
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {},
+            Err(e) => return fail(format!("安装 {} 时出错: {}", tool.name, e)),
+        }
+    };
+
+    // 处理进程退出后剩余的输出
+    while let Ok(line) = receiver.recv_timeout(Duration::from_millis(100)) {
+        handle_line(line);
     }
 
-    // 安装 bun
-    println!("准备安装 bun...");
-    match install_tool(&Tool::Bun) {
-        Ok(_) => info!("bun 安装成功"),
-        Err(e) => warn!("bun 安装失败: {}", e),
+    if !status.success() {
+        let mut err_msg = format!("安装 {} 失败", tool.name);
+        if !recent_logs.is_empty() {
+            err_msg.push_str(&format!(":\n{}", recent_logs.join("\n")));
+        }
+        return fail(err_msg);
     }
-    
-    // 安装 git
-    println!("准备安装 git...");
-    match install_tool(&Tool::Git) {
-        Ok(_) => info!("git 安装成功"),
-        Err(e) => warn!("git 安装失败: {}", e),
+
+    on_progress(&tool.id, InstallProgress::Verify);
+    if !tool.check_installed() {
+        return fail(format!("{} 安装完成，但未检测到可执行文件", tool.name));
     }
 
+    println!("{} 安装完成！", tool.name);
+    info!("{} 安装完成", tool.name);
+    on_progress(&tool.id, InstallProgress::Done { version: tool.version() });
     Ok(())
 }
 
-// 检查工具状态
-#[tauri::command]
-pub fn check_tools_status() -> Result<serde_json::Value, String> {
-    let uv_status = if Tool::Uv.check_installed() {
-        "已安装"
-    } else {
-        "未安装"
+// 版本变化
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum VersionChange {
+    Installed,
+    Upgraded,
+    Downgraded,
+    Unchanged,
+}
+
+// 升级结果
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeReport {
+    pub tool: String,
+    pub previous_version: Option<String>,
+    pub target_version: String,
+    pub new_version: Option<String>,
+    pub change: VersionChange,
+}
+
+// 卸载结果
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallReport {
+    pub tool: String,
+    pub previous_version: Option<String>,
+    pub removed_paths: Vec<String>,
+}
+
+// 按数字逐段比较版本号，如 0.4.30 < 0.5.1
+fn compare_versions(a: &str, b: &str) -> VersionOrdering {
+    let parts = |version: &str| -> Vec<u64> {
+        version.trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
     };
-    
-    let bun_status = if Tool::Bun.check_installed() {
-        "已安装"
+    parts(a).cmp(&parts(b))
+}
+
+// 删除文件或目录
+fn remove_path(path: &Path) -> Result<(), String> {
+    let result = if path.is_dir() {
+        std::fs::remove_dir_all(path)
     } else {
-        "未安装"
+        std::fs::remove_file(path)
     };
-    
-    let git_status = if Tool::Git.check_installed() {
-        "已安装"
-    } else {
-        "未安装"
+    result.map_err(|e| format!("删除 {:?} 失败: {}", path, e))
+}
+
+// 卸载应用安装的工具，系统安装的工具不会被删除
+pub fn uninstall_tool(tool: &ToolSpec) -> Result<UninstallReport, String> {
+    if tool.provided_by_dependency() {
+        return Err(tool.provider_hint("卸载"));
+    }
+    let executable = tool.find_executable()
+        .ok_or_else(|| format!("{} 未安装", tool.name))?;
+    if !tool.is_managed(&executable) {
+        return Err(format!("{} ({}) 不是由本应用安装的，请使用系统包管理器卸载", tool.name, executable.to_string_lossy()));
+    }
+
+    let previous_version = tool.version();
+    let mut removed_paths = Vec::new();
+    for path in tool.existing_managed_paths() {
+        println!("删除 {:?}", path);
+        remove_path(&path)?;
+        removed_paths.push(path.to_string_lossy().to_string());
+    }
+
+    info!("{} 已卸载，删除了 {} 个路径", tool.name, removed_paths.len());
+    Ok(UninstallReport {
+        tool: tool.id.clone(),
+        previous_version,
+        removed_paths,
+    })
+}
+
+// 将工具升级（或降级）到指定版本，未指定时使用 latest 的版本
+//
+// 升级前会把托管路径重命名为备份，安装失败时恢复，因此失败不会留下损坏的工具
+pub fn upgrade_tool(
+    tool: &ToolSpec,
+    version: Option<&str>,
+    on_progress: &dyn Fn(&str, InstallProgress),
+    cancel: &AtomicBool,
+) -> Result<UpgradeReport, String> {
+    // 文件归提供它的依赖管理，版本号也不能传给依赖（npx 的版本不是 Node.js 的版本）
+    if tool.provided_by_dependency() {
+        return Err(tool.provider_hint("升级"));
+    }
+
+    let target_version = match version {
+        Some(version) => version.trim_start_matches('v').to_string(),
+        None => tool.latest.as_ref()
+            .ok_or_else(|| format!("{} 缺少版本号来源", tool.name))?
+            .resolve()?,
     };
-    
-    let status = serde_json::json!({
-        "uv": uv_status,
-        "bun": bun_status,
-        "git": git_status
-    });
-    
-    Ok(status)
+
+    let executable = tool.find_executable();
+    let previous_version = tool.version();
+
+    if let Some(executable) = &executable {
+        if !tool.is_managed(executable) {
+            return Err(format!("{} ({}) 不是由本应用安装的，请使用系统包管理器升级", tool.name, executable.to_string_lossy()));
+        }
+        if previous_version.as_deref().is_some_and(|current| compare_versions(current, &target_version) == VersionOrdering::Equal) {
+            println!("{} 已经是 {} 版本", tool.name, target_version);
+            return Ok(UpgradeReport {
+                tool: tool.id.clone(),
+                previous_version: previous_version.clone(),
+                target_version,
+                new_version: previous_version,
+                change: VersionChange::Unchanged,
+            });
+        }
+    }
+
+    // 备份当前安装
+    let mut backups = Vec::new();
+    for path in tool.existing_managed_paths() {
+        let backup = PathBuf::from(format!("{}.omni-backup", path.to_string_lossy()));
+        if backup.exists() {
+            remove_path(&backup)?;
+        }
+        std::fs::rename(&path, &backup)
+            .map_err(|e| format!("备份 {:?} 失败: {}", path, e))?;
+        backups.push((path, backup));
+    }
+
+    let result = install_tool_with_deps(tool, Some(&target_version), true, on_progress, cancel, &mut Vec::new());
+
+    if let Err(e) = result {
+        // 恢复备份
+        for (path, backup) in &backups {
+            if path.exists() {
+                let _ = remove_path(path);
+            }
+            if let Err(restore_err) = std::fs::rename(backup, path) {
+                error!("恢复 {:?} 失败: {}", path, restore_err);
+            }
+        }
+        return Err(format!("升级 {} 到 {} 失败，已恢复原版本: {}", tool.name, target_version, e));
+    }
+
+    for (_, backup) in &backups {
+        if let Err(e) = remove_path(backup) {
+            warn!("{}", e);
+        }
+    }
+
+    let new_version = tool.version();
+    let change = match &previous_version {
+        None => VersionChange::Installed,
+        Some(previous) => match compare_versions(new_version.as_deref().unwrap_or(&target_version), previous) {
+            VersionOrdering::Greater => VersionChange::Upgraded,
+            VersionOrdering::Less => VersionChange::Downgraded,
+            VersionOrdering::Equal => VersionChange::Unchanged,
+        },
+    };
+
+    info!("{} 版本变化: {:?} -> {:?} ({:?})", tool.name, previous_version, new_version, change);
+    Ok(UpgradeReport {
+        tool: tool.id.clone(),
+        previous_version,
+        target_version,
+        new_version,
+        change,
+    })
 }
 
-// 安装单个工具的命令
+// 检查并安装注册表中标记为 auto_install 的工具
 #[tauri::command]
-pub fn install_single_tool(tool: &str) -> Result<(), String> {
-    info!("开始安装单个工具: {}", tool);
-    
-    let tool = match tool {
-        "uv" => Tool::Uv,
-        "bun" => Tool::Bun,
-        "git" => Tool::Git,
-        _ => return Err("不支持的工具类型".to_string()),
-    };
-    
-    install_tool(&tool)
+pub fn check_and_install_tools() -> Result<(), String> {
+    info!("开始安装工具");
+
+    let cancel = AtomicBool::new(false);
+    let log_progress = |tool: &str, progress: InstallProgress| info!("{}: {:?}", tool, progress);
+
+    for tool in load_registry()?.iter().filter(|tool| tool.auto_install) {
+        println!("准备安装 {}...", tool.id);
+        match install_tool(tool, &log_progress, &cancel) {
+            Ok(_) => info!("{} 安装成功", tool.id),
+            Err(e) => warn!("{} 安装失败: {}", tool.id, e),
+        }
+    }
+
+    Ok(())
 }
+
+// 检查工具状态，返回每个已注册工具的安装状态、版本和路径
+#[tauri::command]
+pub async fn check_tools_status() -> Result<serde_json::Value, String> {
+    let mut status = serde_json::Map::new();
+
+    for tool in load_registry()? {
+        let tool_status = serde_json::to_value(tool.status())
+            .map_err(|e| format!("序列化工具状态失败: {}", e))?;
+        status.insert(tool.id.clone(), tool_status);
+    }
+
+    Ok(serde_json::Value::Object(status))
+}
+
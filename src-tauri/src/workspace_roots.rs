@@ -0,0 +1,199 @@
+use std::path::Path;
+
+use log::info;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::mcp_supervisor::McpSupervisor;
+use crate::sqlite_db::Database;
+
+// 没有选择配置档案时使用的档案
+const DEFAULT_PROFILE: &str = "default";
+// app_settings 中保存当前档案的键
+const ACTIVE_PROFILE_KEY: &str = "workspace_profile";
+
+// 通过 MCP roots 提供给服务器的工作区目录
+#[derive(Serialize, Debug, Clone)]
+pub struct WorkspaceRoot {
+    pub path: String,
+    pub name: String,
+    pub uri: String,
+}
+
+// 配置档案及其工作区目录
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceProfile {
+    pub profile: String,
+    pub active: bool,
+    pub roots: Vec<WorkspaceRoot>,
+}
+
+fn get_db() -> Result<Database, String> {
+    let db = Database::new().map_err(|e| format!("初始化数据库失败: {}", e))?;
+    db.init_workspace_roots_table()
+        .and_then(|_| db.init_app_settings_table())
+        .map_err(|e| format!("初始化表失败: {}", e))?;
+    Ok(db)
+}
+
+// 把本地路径转换为 file:// URI，对路径以外的字符做百分号编码
+pub fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    // Windows 的盘符路径需要以 / 开头，如 file:///C:/work
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+pub fn active_profile() -> Result<String, String> {
+    let profile = get_db()?.get_setting(ACTIVE_PROFILE_KEY).filter(|profile| !profile.is_empty());
+    Ok(profile.unwrap_or_else(|| DEFAULT_PROFILE.to_string()))
+}
+
+pub fn list_roots(profile: &str) -> Result<Vec<WorkspaceRoot>, String> {
+    let db = get_db()?;
+    let conn = db.get_connection();
+    let mut stmt = conn.prepare("SELECT path, name FROM workspace_roots WHERE profile = ?1 ORDER BY path")
+        .map_err(|e| format!("准备查询语句失败: {}", e))?;
+    let roots = stmt.query_map([profile], |row| {
+        let path: String = row.get(0)?;
+        let name: Option<String> = row.get(1)?;
+        Ok(WorkspaceRoot {
+            uri: file_uri(Path::new(&path)),
+            name: name.unwrap_or_else(|| path.clone()),
+            path,
+        })
+    })
+    .map_err(|e| format!("查询工作区目录失败: {}", e))?
+    .filter_map(|root| root.ok())
+    .collect();
+    Ok(roots)
+}
+
+fn list_profiles() -> Result<Vec<WorkspaceProfile>, String> {
+    let active = active_profile()?;
+    let db = get_db()?;
+    let conn = db.get_connection();
+    let mut stmt = conn.prepare("SELECT DISTINCT profile FROM workspace_roots ORDER BY profile")
+        .map_err(|e| format!("准备查询语句失败: {}", e))?;
+    let mut names: Vec<String> = stmt.query_map([], |row| row.get(0))
+        .map_err(|e| format!("查询配置档案失败: {}", e))?
+        .filter_map(|name| name.ok())
+        .collect();
+    if !names.contains(&active) {
+        names.insert(0, active.clone());
+    }
+
+    names.into_iter()
+        .map(|profile| Ok(WorkspaceProfile {
+            roots: list_roots(&profile)?,
+            active: profile == active,
+            profile,
+        }))
+        .collect()
+}
+
+fn add_roots(profile: &str, paths: &[std::path::PathBuf]) -> Result<(), String> {
+    let db = get_db()?;
+    for path in paths {
+        if !path.is_dir() {
+            return Err(format!("{} 不是目录", path.to_string_lossy()));
+        }
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string());
+        db.get_connection().execute(
+            "INSERT OR REPLACE INTO workspace_roots (profile, path, name) VALUES (?1, ?2, ?3)",
+            rusqlite::params![profile, path.to_string_lossy(), name],
+        ).map_err(|e| format!("保存工作区目录失败: {}", e))?;
+    }
+    Ok(())
+}
+
+// roots/list 的返回结果，使用当前配置档案的目录
+pub fn roots_list_result() -> Result<Value, String> {
+    let roots = list_roots(&active_profile()?)?;
+    Ok(json!({
+        "roots": roots.iter()
+            .map(|root| json!({ "uri": root.uri, "name": root.name }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+// 当前档案的目录变化后通知所有运行中的服务器
+fn notify_changed(app: &AppHandle, profile: &str) -> Result<(), String> {
+    if profile == active_profile()? {
+        app.state::<McpSupervisor>().notify_all("notifications/roots/list_changed", json!({}));
+    }
+    Ok(())
+}
+
+fn resolve_profile(profile: Option<String>) -> Result<String, String> {
+    match profile.filter(|profile| !profile.trim().is_empty()) {
+        Some(profile) => Ok(profile.trim().to_string()),
+        None => active_profile(),
+    }
+}
+
+// 列出配置档案及其工作区目录
+#[tauri::command]
+pub fn get_workspace_profiles() -> Result<Vec<WorkspaceProfile>, String> {
+    list_profiles()
+}
+
+// 通过系统对话框选择工作区目录并加入配置档案，profile 为空时使用当前档案
+#[tauri::command]
+pub async fn pick_workspace_roots(app: AppHandle, profile: Option<String>) -> Result<Vec<WorkspaceRoot>, String> {
+    let profile = resolve_profile(profile)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let Some(folders) = app.dialog().file().set_title("选择工作区目录").blocking_pick_folders() else {
+            return list_roots(&profile);
+        };
+        let paths = folders.into_iter()
+            .map(|folder| folder.into_path().map_err(|e| format!("无法解析所选目录: {}", e)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        add_roots(&profile, &paths)?;
+        info!("工作区 {} 添加目录: {:?}", profile, paths);
+        notify_changed(&app, &profile)?;
+        list_roots(&profile)
+    })
+    .await
+    .map_err(|e| format!("选择目录异常结束: {}", e))?
+}
+
+// 从配置档案中移除工作区目录
+#[tauri::command]
+pub fn remove_workspace_root(app: AppHandle, profile: Option<String>, path: String) -> Result<Vec<WorkspaceRoot>, String> {
+    let profile = resolve_profile(profile)?;
+    get_db()?.get_connection().execute(
+        "DELETE FROM workspace_roots WHERE profile = ?1 AND path = ?2",
+        [&profile, &path],
+    ).map_err(|e| format!("删除工作区目录失败: {}", e))?;
+
+    info!("工作区 {} 移除目录: {}", profile, path);
+    notify_changed(&app, &profile)?;
+    list_roots(&profile)
+}
+
+// 切换当前配置档案，服务器会收到目录变更通知
+#[tauri::command]
+pub fn set_workspace_profile(app: AppHandle, profile: String) -> Result<(), String> {
+    let profile = profile.trim();
+    if profile.is_empty() {
+        return Err("配置档案名称不能为空".to_string());
+    }
+    get_db()?.set_setting(ACTIVE_PROFILE_KEY, profile).map_err(|e| format!("保存配置档案失败: {}", e))?;
+
+    info!("切换到工作区配置档案 {}", profile);
+    notify_changed(&app, profile)
+}
@@ -6,6 +6,24 @@ interface Message {
   content: string;
   role: 'user' | 'assistant';
   timestamp: number;
+  // 作为上下文附加的 MCP 资源
+  attachments?: ResourceAttachment[];
+}
+
+// 附加到消息中的 MCP 资源内容
+export interface ResourceAttachment {
+  server: string;
+  uri: string;
+  mimeType?: string | null;
+  text: string;
+}
+
+// MCP 资源列表中的条目
+export interface McpResource {
+  uri: string;
+  name?: string;
+  description?: string;
+  mimeType?: string;
 }
 
 interface Conversation {
@@ -38,6 +56,7 @@ interface McpServer {
   env: Record<string, any>;
   type: string;
   baseUrl: string;
+  runtimeStatus?: { status: string; tool?: string | null; command?: string } | null;
 }
 export const mcpServers = ref<McpServer[]>([]);
 export const selectedMcpServers = ref<string[]>([]);
@@ -112,6 +131,109 @@ let abortController = new AbortController();
 // 确保所有必要的变量和函数都已正确导出
 export const newMessage = ref('');
 export const loading = ref(false);
+// 下一条消息要附加的资源
+export const pendingAttachments = ref<ResourceAttachment[]>([]);
+export const resourceServer = ref('');
+export const availableResources = ref<McpResource[]>([]);
+
+// 列出服务器的资源，服务器没有运行时后端会先启动它
+export async function loadResources(server: string) {
+  resourceServer.value = server;
+  availableResources.value = [];
+  if (!server) return;
+  try {
+    availableResources.value = await invoke('list_mcp_resources', { server }) as McpResource[];
+  } catch (error) {
+    console.error('获取资源列表失败:', error);
+  }
+}
+
+export async function attachResource(uri: string) {
+  if (!resourceServer.value || pendingAttachments.value.some(item => item.uri === uri)) return;
+  try {
+    const attachment = await invoke('attach_mcp_resource', { server: resourceServer.value, uri }) as ResourceAttachment;
+    pendingAttachments.value.push(attachment);
+  } catch (error) {
+    console.error('读取资源失败:', error);
+  }
+}
+
+export function removeAttachment(uri: string) {
+  pendingAttachments.value = pendingAttachments.value.filter(item => item.uri !== uri);
+}
+
+// MCP 服务器提供的提示词模板
+export interface McpPrompt {
+  server: string;
+  name: string;
+  title?: string | null;
+  description?: string | null;
+  arguments: { name: string; description?: string | null; required: boolean }[];
+}
+
+export const mcpPrompts = ref<McpPrompt[]>([]);
+export const selectedPrompt = ref<McpPrompt | null>(null);
+export const promptArguments = ref<Record<string, string>>({});
+
+// 输入以 / 开头时按名称匹配的提示词
+export const matchingPrompts = computed(() => {
+  if (!newMessage.value.startsWith('/')) return [];
+  const keyword = newMessage.value.slice(1).trim().toLowerCase();
+  return mcpPrompts.value.filter(prompt => `${prompt.server}:${prompt.name}`.toLowerCase().includes(keyword));
+});
+
+export async function loadPrompts() {
+  try {
+    mcpPrompts.value = await invoke('list_mcp_prompts') as McpPrompt[];
+  } catch (error) {
+    console.error('获取提示词失败:', error);
+  }
+}
+
+export function selectPrompt(prompt: McpPrompt) {
+  selectedPrompt.value = prompt;
+  promptArguments.value = Object.fromEntries(prompt.arguments.map(argument => [argument.name, '']));
+  newMessage.value = '';
+}
+
+// 渲染提示词，最后一条用户消息放入输入框，其余消息加入当前对话
+export async function applyPrompt() {
+  const prompt = selectedPrompt.value;
+  if (!prompt || !currentConversation.value) return;
+  try {
+    const messages = await invoke('get_mcp_prompt', {
+      server: prompt.server,
+      name: prompt.name,
+      arguments: promptArguments.value,
+    }) as { role: 'user' | 'assistant'; content: string }[];
+    const last = messages[messages.length - 1];
+    const history = last?.role === 'user' ? messages.slice(0, -1) : messages;
+    history.forEach((message, index) => {
+      currentConversation.value?.messages.push({
+        id: `${Date.now()}-${index}`,
+        content: message.content,
+        role: message.role,
+        timestamp: Date.now(),
+      });
+    });
+    newMessage.value = last?.role === 'user' ? last.content : '';
+    selectedPrompt.value = null;
+    saveConversations();
+    scrollToBottom();
+  } catch (error) {
+    console.error('渲染提示词失败:', error);
+    alert(`渲染提示词失败: ${error}`);
+  }
+}
+
+// 把附加的资源作为上下文放在消息正文之前发送给模型
+function contentWithAttachments(message: Message): string {
+  if (!message.attachments?.length) return message.content;
+  const context = message.attachments
+    .map(item => `<resource server="${item.server}" uri="${item.uri}">\n${item.text}\n</resource>`)
+    .join('\n');
+  return `${context}\n\n${message.content}`;
+}
 export const availableModels = ref(['openai', 'ollama']); // 改为响应式数组
 export const currentModel = ref('openai');
 export const modelConfigs = ref<Record<string, ModelConfig>>({});
@@ -301,7 +423,8 @@ export async function sendMessage() {
     id: Date.now().toString(),
     content: newMessage.value,
     role: 'user',
-    timestamp: Date.now()
+    timestamp: Date.now(),
+    attachments: pendingAttachments.value.length ? [...pendingAttachments.value] : undefined
   };
   
   // 如果是对话的第一条消息，则更新对话标题
@@ -316,6 +439,7 @@ export async function sendMessage() {
   
   currentConversation.value.messages.push(userMessage);
   newMessage.value = '';
+  pendingAttachments.value = [];
   loading.value = true;
   streamingContent.value = '';
   
@@ -327,7 +451,7 @@ export async function sendMessage() {
     const mcpResponse = await fetch(`${currentModelConfig.value.api_url}/chat`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
-      body: JSON.stringify({ message: userMessage.content }),
+      body: JSON.stringify({ message: contentWithAttachments(userMessage) }),
       signal: abortController.signal // 绑定中止信号
     });
 
@@ -414,7 +538,7 @@ export async function sendMessage() {
       body = JSON.stringify({
         model: modelName,
         messages: [
-          { role: 'user', content: userMessage.content }
+          { role: 'user', content: contentWithAttachments(userMessage) }
         ],
         stream: true
       });
@@ -425,7 +549,7 @@ export async function sendMessage() {
       body = JSON.stringify({
         model: modelName,
         messages: [
-          { role: 'user', content: userMessage.content }
+          { role: 'user', content: contentWithAttachments(userMessage) }
         ],
         stream: true
       });
@@ -440,7 +564,7 @@ export async function sendMessage() {
       body = JSON.stringify({
         model: modelName,
         messages: [
-          { role: 'user', content: userMessage.content }
+          { role: 'user', content: contentWithAttachments(userMessage) }
         ],
         stream: true
       });